@@ -1,34 +1,53 @@
 use deno_runtime::deno_core;
-use deno_core::{ModuleSpecifier,FeatureChecker,SharedArrayBufferStore,CompiledWasmModuleStore};
+use deno_core::{v8,JsRuntime,ModuleSpecifier,FeatureChecker,SharedArrayBufferStore,CompiledWasmModuleStore};
+use deno_core::parking_lot::Mutex;
+pub use deno_core::Extension;
 use deno_core::error::generic_error;
 use deno_runtime::{BootstrapOptions, WorkerExecutionMode};
 use deno_runtime::deno_broadcast_channel::InMemoryBroadcastChannel;
 use deno_runtime::worker::{MainWorker, WorkerOptions};
-use deno_runtime::deno_permissions::PermissionsContainer;
+use deno_runtime::deno_permissions::{Permissions,PermissionsContainer};
+pub use deno_runtime::deno_permissions::PermissionsOptions;
 use deno_runtime::inspector_server::InspectorServer;
-use deno_runtime::deno_tls::RootCertStoreProvider;
+pub use deno_runtime::deno_tls::RootCertStoreProvider;
+pub use deno_runtime::deno_io::{Stdio,StdioPipe};
 use deno_runtime::deno_web::BlobStore;
+use deno_runtime::code_cache::CodeCache;
 use deno_cache_dir::GlobalHttpCache;
 use import_map::ImportMap;
 
+use std::collections::HashSet;
 use std::net::SocketAddr;
 use std::path::{Path,PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc,OnceLock};
 use std::rc::Rc;
 
 use velcro::vec;
 
-use or_panic::OrPanic;
-
 mod util;
-pub use util::AnyError;
-use util::{FileFetcher,File,SJSModuleLoader,SJSCacheEnv,HttpClient,CacheSetting,BasicRootCertStoreProvider};
+pub use util::{AnyError,SjsError,CacheSetting,Config,LOCKFILE_NAME};
+use util::{FileFetcher,File,SJSModuleLoader,SJSCacheEnv,HttpClient,BasicRootCertStoreProvider,SourceMapStore,TranspileCache,SJSCodeCache,Lockfile,NpmResolver};
 use util::path::ToAbsolutePath as _;
 use util::url::resolve_maybe_url;
+use util::watch::WatchedFiles;
+
+mod runtime;
+pub use runtime::{Runtime,RuntimeBuilder,ExtensionFactory};
+
+mod tools;
+pub use tools::{test,bundle,compile,load_standalone,run_standalone,Standalone,repl,cache,info,watch,WatchOptions,vendor};
 
 static STARTUP_SNAPSHOT: &[u8] =
     include_bytes!(concat!(env!("OUT_DIR"), "/STARTUP_SNAPSHOT.bin"));
 
+/// Extension modules run before `system` is created, so what they stage under `Symbol.for("sjs.system")`
+/// is copied onto it once a worker has bootstrapped
+const EXPOSE_EXTENSIONS: &str = r#"(() => {
+    const staged = globalThis[Symbol.for("sjs.system")];
+    delete globalThis[Symbol.for("sjs.system")];
+    Object.assign(globalThis.system, staged);
+})();"#;
+
 pub fn version() -> &'static str {
     return env!("CARGO_PKG_VERSION");
 }
@@ -61,6 +80,86 @@ pub struct InspectorOptions {
     pub wait: bool
 }
 
+#[derive(Clone)]
+pub struct RuntimeOptions {
+    pub macros: Vec<String>,
+    pub include_paths: Vec<String>,
+    pub allow_remote: bool,
+    pub import_map_source: Option<String>,
+    pub cache_setting: CacheSetting,
+    pub permissions: PermissionsOptions,
+    /// Names from `deno_runtime::UNSTABLE_GRANULAR_FLAGS` to enable in addition to the defaults
+    pub unstable_features: Vec<String>,
+    /// Reuse compiled modules from earlier runs
+    pub transpile_cache: bool,
+    /// Where to record and check the hashes of remote modules
+    pub lockfile: Option<PathBuf>,
+    /// Replace the lockfile's hashes with those of the current sources
+    pub lock_write: bool,
+    /// The registry `npm:` packages are downloaded from, by default `$NPM_CONFIG_REGISTRY` or the public registry
+    pub npm_registry: Option<String>,
+    /// Expose Node's `process`, `Buffer` and `global` before the main module runs
+    pub node_globals: bool,
+    /// Where remote modules, npm packages and caches are kept, by default `~/.sjs`
+    pub storage_dir: Option<PathBuf>,
+    /// Where per-program caches are kept and the fallback storage directory, by default `$TMPDIR/sjs`
+    pub temp_dir: Option<PathBuf>,
+    /// The root certificates trusted when fetching modules and in scripts, by default the system's and Mozilla's
+    pub root_cert_store_provider: Option<Arc<dyn RootCertStoreProvider>>,
+    /// Seeds `Math.random` and `crypto.getRandomValues` for reproducible runs
+    pub seed: Option<u64>,
+}
+
+impl Default for RuntimeOptions {
+    fn default() -> Self {
+        Self {
+            macros: vec![],
+            include_paths: vec![],
+            allow_remote: false,
+            import_map_source: None,
+            cache_setting: CacheSetting::Use,
+            permissions: allow_all_permissions(),
+            unstable_features: vec![],
+            transpile_cache: true,
+            lockfile: None,
+            lock_write: false,
+            npm_registry: None,
+            node_globals: false,
+            storage_dir: None,
+            temp_dir: None,
+            root_cert_store_provider: None,
+            seed: None,
+        }
+    }
+}
+
+impl RuntimeOptions {
+    fn storage_directory(&self) -> PathBuf {
+        self.storage_dir.clone().or_else(get_storage_directory).unwrap_or_else(|| self.temp_directory())
+    }
+
+    fn temp_directory(&self) -> PathBuf {
+        self.temp_dir.clone().unwrap_or_else(get_temp_directory)
+    }
+}
+
+/// Grants every permission, an empty allow list allows everything
+pub fn allow_all_permissions() -> PermissionsOptions {
+    PermissionsOptions {
+        allow_env: Some(vec![]),
+        allow_hrtime: true,
+        allow_net: Some(vec![]),
+        allow_ffi: Some(vec![]),
+        allow_read: Some(vec![]),
+        allow_run: Some(vec![]),
+        allow_sys: Some(vec![]),
+        allow_write: Some(vec![]),
+        prompt: false,
+
+        ..Default::default()
+    }
+}
+
 #[derive(Clone)]
 pub struct SharedState {
     args0: String,
@@ -85,11 +184,30 @@ pub struct SharedState {
     macros: Vec<String>,
     include_paths: Vec<String>,
 
-    import_map: Option<ImportMap>,
+    import_map: Option<Arc<ImportMap>>,
+
+    precompiled: Arc<HashSet<ModuleSpecifier>>,
+
+    watched_files: Option<WatchedFiles>,
+
+    source_maps: SourceMapStore,
+
+    transpile_cache: Option<TranspileCache>,
+
+    code_cache: Option<Arc<SJSCodeCache>>,
+
+    npm_resolver: Option<Arc<NpmResolver>>,
+
+    storage_dir: PathBuf,
+    temp_dir: PathBuf,
+
+    /// Installed into the main worker and every web worker
+    extension_factories: Vec<ExtensionFactory>,
 }
 
 impl Default for SharedState {
     fn default() -> Self {
+        let blob_store: Arc<BlobStore> = Default::default();
         Self {
             args0: String::new(),
             args: vec![],
@@ -98,12 +216,11 @@ impl Default for SharedState {
 
             shared_array_buffer_store: Some(Default::default()),
             compiled_wasm_module_store: Some(Default::default()),
-            blob_store: Default::default(),
+            blob_store: blob_store.clone(),
 
             seed: None,
 
-            // See deno_runtime::UNSTABLE_GRANULAR_FLAGS
-            unstable_features: vec![..(1..=8),11],
+            unstable_features: get_unstable_features(&[]),
 
             inspector: None,
 
@@ -112,22 +229,75 @@ impl Default for SharedState {
                 CacheSetting::Use,
                 false,
                 Arc::new(HttpClient::new(Default::default(),None)),
-                Default::default(),
+                blob_store,
             )),
             root_cert_store_provider: Some(Arc::new(BasicRootCertStoreProvider::default())),
 
             macros: vec![],
             include_paths: vec![],
 
-            import_map: None
+            import_map: None,
+
+            precompiled: Default::default(),
+
+            watched_files: None,
+
+            source_maps: Default::default(),
+
+            transpile_cache: None,
+
+            code_cache: None,
+
+            npm_resolver: None,
+
+            storage_dir: get_storage_directory().unwrap_or_else(|| get_temp_directory()),
+            temp_dir: get_temp_directory(),
+
+            extension_factories: vec![],
         }
     }
 }
 
-fn create_feature_checker(unstable_features: &Vec<i32>) -> Arc<FeatureChecker> {
+/// Maps feature names to their ids, see deno_runtime::UNSTABLE_GRANULAR_FLAGS
+fn get_unstable_features(names: &[String]) -> Vec<i32> {
+    let mut unstable_features = vec![..(1..=8),11];
+    for (flag_name, _, i) in deno_runtime::UNSTABLE_GRANULAR_FLAGS {
+        if names.iter().any(|name| name == flag_name) && !unstable_features.contains(i) {
+            unstable_features.push(*i);
+        }
+    }
+    unstable_features
+}
+
+/// Stops a worker that calls an unstable API which is not enabled. The feature checker cannot fail the
+/// op, so the isolate is terminated instead and the error kept in the worker's op state
+#[derive(Clone, Default)]
+struct UnstableApiGuard {
+    isolate: Arc<OnceLock<v8::IsolateHandle>>,
+    api_name: Arc<Mutex<Option<String>>>,
+}
+
+impl UnstableApiGuard {
+    fn watch(&self, js_runtime: &mut JsRuntime) {
+        let _ = self.isolate.set(js_runtime.v8_isolate().thread_safe_handle());
+        js_runtime.op_state().borrow_mut().put(self.clone());
+    }
+}
+
+/// The reason a worker was stopped by its `UnstableApiGuard`, which replaces the termination error
+fn unstable_api_error(js_runtime: &mut JsRuntime) -> Option<AnyError> {
+    let guard = js_runtime.op_state().borrow().try_borrow::<UnstableApiGuard>().cloned()?;
+    let api_name = guard.api_name.lock().take()?;
+    Some(SjsError::UnsupportedUnstableApi(api_name).into())
+}
+
+fn create_feature_checker(unstable_features: &Vec<i32>, guard: UnstableApiGuard) -> Arc<FeatureChecker> {
     let mut feature_checker = FeatureChecker::default();
-    feature_checker.set_exit_cb(Box::new(|_feature: &str, api_name: &str| {
-        panic!("Unstable API '{api_name}' is not supported!");
+    feature_checker.set_exit_cb(Box::new(move |_feature: &str, api_name: &str| {
+        guard.api_name.lock().get_or_insert_with(|| api_name.to_string());
+        if let Some(isolate) = guard.isolate.get() {
+            isolate.terminate_execution();
+        }
     }));
 
     for (flag_name, _, i) in deno_runtime::UNSTABLE_GRANULAR_FLAGS {
@@ -146,6 +316,9 @@ fn create_web_worker_callback(shared: Arc<SharedState>) -> Arc<deno_runtime::ops
         
         mtsc::init_v8(false);
 
+        let unstable_api_guard = UnstableApiGuard::default();
+        let module_loader = Rc::new(create_module_loader(&shared, args.permissions.clone(), args.permissions.clone()));
+
         let options = WebWorkerOptions {
             bootstrap: BootstrapOptions {
                 args: vec![shared.args0.clone(), ..shared.args.clone()],
@@ -158,18 +331,19 @@ fn create_web_worker_callback(shared: Arc<SharedState>) -> Arc<deno_runtime::ops
 
                 ..Default::default()
             },
-            extensions: vec![],
+            extensions: shared.extension_factories.iter().map(|factory| factory()).collect(),
             startup_snapshot: Some(STARTUP_SNAPSHOT),
             unsafely_ignore_certificate_errors: None,
             root_cert_store_provider: shared.root_cert_store_provider.clone(),
             seed: shared.seed,
             fs: Arc::new(deno_runtime::deno_fs::RealFs),
-            module_loader: Rc::new(SJSModuleLoader {file_fetcher: shared.file_fetcher.clone(), macros: shared.macros.clone(), include_paths: shared.include_paths.clone(), import_map: shared.import_map.clone()}),
+            module_loader: module_loader.clone(),
+            custom_module_evaluation_cb: Some(Box::new(util::evaluate_raw_module)),
             node_resolver: None,
             npm_resolver: None,
             create_web_worker_cb: create_web_worker_callback(shared.clone()),
             format_js_error_fn: Some(Arc::new(deno_runtime::fmt_errors::format_js_error)),
-            source_map_getter: None, // Source maps not implemented, may change in future
+            source_map_getter: Some(Rc::new(shared.source_maps.clone())),
             worker_type: args.worker_type,
             maybe_inspector_server: shared.inspector.clone(),
             get_error_class_fn: Some(&(|e| deno_runtime::errors::get_error_class_name(e).unwrap_or("Error"))),
@@ -177,21 +351,29 @@ fn create_web_worker_callback(shared: Arc<SharedState>) -> Arc<deno_runtime::ops
             broadcast_channel: shared.broadcast_channel.clone(),
             shared_array_buffer_store: shared.shared_array_buffer_store.clone(),
             compiled_wasm_module_store: shared.compiled_wasm_module_store.clone(),
-            cache_storage_dir: Some(get_temp_directory().join(util::hash(args.main_module.to_string().as_bytes()))),
+            cache_storage_dir: Some(shared.temp_dir.join(util::hash(args.main_module.to_string().as_bytes()))),
             stdio: Default::default(),
-            feature_checker: create_feature_checker(&shared.unstable_features),
+            feature_checker: create_feature_checker(&shared.unstable_features, unstable_api_guard.clone()),
             strace_ops: None,
             close_on_idle: args.close_on_idle,
             maybe_worker_metadata: args.maybe_worker_metadata
         };
 
-        WebWorker::bootstrap_from_options(
+        let (mut worker, handle) = WebWorker::bootstrap_from_options(
             args.name,
             args.permissions,
             args.main_module,
             args.worker_id,
             options
-        )
+        );
+        unstable_api_guard.watch(&mut worker.js_runtime);
+        if !shared.extension_factories.is_empty() {
+            if let Err(error) = worker.execute_script("[sjs:extensions]", EXPOSE_EXTENSIONS.to_string().into()) {
+                // The callback cannot fail, so the error is reported by the worker failing to load its main module
+                *module_loader.startup_error.lock() = Some(SjsError::WorkerStartup { specifier: worker.main_module.clone(), error });
+            }
+        }
+        (worker, handle)
     })
 }
 
@@ -206,29 +388,85 @@ pub fn get_temp_directory() -> PathBuf {
     std::env::temp_dir().join("sjs")
 }
 
-pub async fn run(input: ScriptSource, args: Vec<String>, macros: Vec<String>, include_paths: Vec<String>, allow_remote: bool, import_map_source: Option<String>, inspector_options: InspectorOptions) -> Result<(), AnyError> {
+pub async fn run(input: ScriptSource, args: Vec<String>, options: RuntimeOptions, inspector_options: InspectorOptions) -> Result<(), AnyError> {
     init_v8();
 
-    let args0 = match input.clone() {
-        ScriptSource::File(source_path) => Path::new(&source_path).absolute().map(|x| String::from(x.into_os_string().into_string().unwrap())).map_err(|x| format!("{}: {}",source_path,x)).or_panic(),
-        ScriptSource::URL(source_url) => source_url,
-        ScriptSource::FileOrURL(source_path) => {
-            ModuleSpecifier::parse(&source_path).map(|_| source_path.clone()).unwrap_or_else(|_|
-                Path::new(&source_path).absolute().map(|x| String::from(x.into_os_string().into_string().unwrap())).map_err(|x| format!("{}: {}",source_path,x)).or_panic()
-            )
-        }
-        _ => String::new()
-    };
+    let args0 = get_args0(&input)?;
+    let inspector = create_inspector_server(&inspector_options)?;
+    execute(input, args0, args, &options, inspector, inspector_options.wait, None).await
+}
+
+fn get_args0(input: &ScriptSource) -> Result<String, SjsError> {
+    match input.clone() {
+        ScriptSource::File(source_path) => absolute_source_path(&source_path),
+        ScriptSource::URL(source_url) => Ok(source_url),
+        ScriptSource::FileOrURL(source_path) => match ModuleSpecifier::parse(&source_path) {
+            Ok(_) => Ok(source_path),
+            Err(_) => absolute_source_path(&source_path)
+        },
+        _ => Ok(String::new())
+    }
+}
+
+fn absolute_source_path(source_path: &str) -> Result<String, SjsError> {
+    Path::new(source_path).absolute()
+        .map_err(|x| SjsError::invalid_source(source_path, x))?
+        .into_os_string().into_string()
+        .map_err(|_| SjsError::invalid_source(source_path, "Path is not valid UTF-8"))
+}
+
+fn create_inspector_server(inspector_options: &InspectorOptions) -> Result<Option<Arc<InspectorServer>>, AnyError> {
+    Ok(match inspector_options.port {
+        Some(port) => {
+            let host = SocketAddr::from(([127, 0, 0, 1], port));
+            Some(Arc::new(InspectorServer::new(host, util::get_user_agent())?))
+        },
+        _ => None
+    })
+}
+
+/// Runs a program until its event loop finishes, the local files it loads are sent to `watched_files` if given
+async fn execute(input: ScriptSource, args0: String, args: Vec<String>, options: &RuntimeOptions, inspector: Option<Arc<InspectorServer>>, wait_for_inspector: bool, watched_files: Option<WatchedFiles>) -> Result<(), AnyError> {
+    let mut runtime = runtime::create_runtime(input, args0, args, options, inspector, wait_for_inspector, watched_files, Default::default()).await?;
+    runtime.run().await?;
+    Ok(())
+}
+
+/// The dependency cache remote modules are stored in
+fn create_http_cache(options: &RuntimeOptions) -> Arc<GlobalHttpCache<SJSCacheEnv>> {
+    Arc::new(GlobalHttpCache::<SJSCacheEnv>::new(options.storage_directory().join("libs"), SJSCacheEnv))
+}
+
+fn create_npm_resolver(options: &RuntimeOptions, lockfile: Option<Arc<Lockfile>>) -> Arc<NpmResolver> {
+    let registry = options.npm_registry.clone()
+        .or_else(|| std::env::var("NPM_CONFIG_REGISTRY").ok().filter(|registry| !registry.is_empty()))
+        .unwrap_or_else(|| String::from(util::DEFAULT_NPM_REGISTRY));
+    Arc::new(NpmResolver::new(
+        options.storage_directory().join("npm"),
+        registry,
+        Arc::new(HttpClient::new(options.root_cert_store_provider.clone(),None)),
+        options.cache_setting.clone(),
+        options.allow_remote,
+        lockfile,
+    ))
+}
 
-    let file_fetcher = Arc::new(FileFetcher::new(
-        Arc::new(GlobalHttpCache::<SJSCacheEnv>::new(get_storage_directory().unwrap_or_else(|| get_temp_directory()).join("libs"), SJSCacheEnv)),
-        CacheSetting::Use,
-        allow_remote,
-        Arc::new(HttpClient::new(Default::default(),None)),
+fn create_file_fetcher(options: &RuntimeOptions) -> Result<Arc<FileFetcher>, AnyError> {
+    let mut file_fetcher = FileFetcher::new(
+        create_http_cache(options),
+        options.cache_setting.clone(),
+        options.allow_remote,
+        Arc::new(HttpClient::new(options.root_cert_store_provider.clone(),None)),
         Default::default(),
-    ));
+    );
+    if let Some(path) = &options.lockfile {
+        file_fetcher.set_lockfile(Some(Arc::new(Lockfile::new(path.clone(), options.lock_write)?)));
+    }
+    Ok(Arc::new(file_fetcher))
+}
 
-    let main_module = match input {
+fn resolve_main_module(input: ScriptSource, file_fetcher: &FileFetcher) -> Result<ModuleSpecifier, SjsError> {
+    Ok(match input {
         ScriptSource::Text(source_text) => {
             let main_module = ModuleSpecifier::parse("sjs://text").unwrap();
             let bytes: Vec<u8> = source_text.into();
@@ -240,38 +478,91 @@ pub async fn run(input: ScriptSource, args: Vec<String>, macros: Vec<String>, in
             main_module
         }
         ScriptSource::File(source_path) => {
-            ModuleSpecifier::from_file_path(Path::new(&source_path).absolute().map_err(|x| format!("{}: {}",source_path,x)).or_panic().as_path()).map_err(|_x| AnyError::msg(format!("{}: {}",source_path,"Invalid file or URL"))).or_panic()
+            let path = Path::new(&source_path).absolute().map_err(|x| SjsError::invalid_source(&source_path, x))?;
+            ModuleSpecifier::from_file_path(path.as_path()).map_err(|_| SjsError::invalid_source(&source_path, "Invalid file or URL"))?
         }
         ScriptSource::URL(source_url) => {
-            ModuleSpecifier::parse(&source_url).or_panic()
+            ModuleSpecifier::parse(&source_url).map_err(|x| SjsError::invalid_source(&source_url, x))?
         },
         ScriptSource::FileOrURL(source_path) => {
-            resolve_maybe_url(source_path).or_panic()
+            resolve_maybe_url(&source_path).map_err(|_| SjsError::invalid_source(&source_path, "Invalid file or URL"))?
         }
-    };
+    })
+}
 
-    let shared = Arc::new(SharedState {
+async fn create_shared_state(main_module: &ModuleSpecifier, args0: String, args: Vec<String>, file_fetcher: Arc<FileFetcher>, options: &RuntimeOptions, inspector: Option<Arc<InspectorServer>>) -> Result<Arc<SharedState>, SjsError> {
+    let npm_resolver = create_npm_resolver(options, file_fetcher.lockfile());
+    Ok(Arc::new(SharedState {
         args0,
         args,
-        inspector: match inspector_options.port {
-            Some(port) => {
-                let host = format!("127.0.0.1:{port}").parse::<SocketAddr>().unwrap();
-                Some(Arc::new(InspectorServer::new(host, util::get_user_agent()).unwrap()))
-            },
-            _ => None
-        },
+        inspector,
 
-        import_map: create_import_map(file_fetcher.clone(), &main_module, import_map_source, true).await,
+        import_map: create_import_map(file_fetcher.clone(), main_module, options.import_map_source.clone(), true).await?.map(Arc::new),
         
+        blob_store: file_fetcher.blob_store().clone(),
         file_fetcher,
 
-        macros,
-        include_paths,
+        macros: options.macros.clone(),
+        include_paths: options.include_paths.clone(),
+
+        unstable_features: get_unstable_features(&options.unstable_features),
+
+        transpile_cache: options.transpile_cache.then(|| TranspileCache::new(options.storage_directory().join("gen"))),
+        code_cache: Some(Arc::new(SJSCodeCache::new(options.storage_directory().join("v8_code_cache")))),
+        npm_resolver: Some(npm_resolver),
+
+        seed: options.seed,
+        root_cert_store_provider: Some(options.root_cert_store_provider.clone().unwrap_or_else(|| Arc::new(BasicRootCertStoreProvider::default()))),
+
+        storage_dir: options.storage_directory(),
+        temp_dir: options.temp_directory(),
 
         ..Default::default()
-    });
+    }))
+}
+
+fn create_module_loader(shared: &SharedState, root_permissions: PermissionsContainer, permissions: PermissionsContainer) -> SJSModuleLoader {
+    SJSModuleLoader {
+        file_fetcher: shared.file_fetcher.clone(),
+        macros: shared.macros.clone(),
+        include_paths: shared.include_paths.clone(),
+        import_map: shared.import_map.clone(),
+        root_permissions,
+        permissions,
+        precompiled: shared.precompiled.clone(),
+        watched_files: shared.watched_files.clone(),
+        source_maps: shared.source_maps.clone(),
+        transpile_cache: shared.transpile_cache.clone(),
+        code_cache: shared.code_cache.clone(),
+        npm_resolver: shared.npm_resolver.clone(),
+        startup_error: Default::default()
+    }
+}
 
+/// Creates a module loader for tools that walk the module graph without running it
+async fn create_tool_loader(main_module: &ModuleSpecifier, options: &RuntimeOptions) -> Result<SJSModuleLoader, AnyError> {
+    let file_fetcher = create_file_fetcher(options)?;
+    let permissions = create_permissions(options)?;
+    let shared = create_shared_state(main_module, main_module.to_string(), vec![], file_fetcher, options, None).await?;
+    Ok(create_module_loader(&shared, root_permissions(), permissions))
+}
+
+/// Like Deno, the import map and the main worker's static module graph are loaded whatever the program's permissions
+fn root_permissions() -> PermissionsContainer {
+    PermissionsContainer::new(Permissions::allow_all())
+}
 
+fn create_permissions(options: &RuntimeOptions) -> Result<PermissionsContainer, AnyError> {
+    Ok(PermissionsContainer::new(Permissions::from_options(&options.permissions)?))
+}
+
+fn create_main_worker(shared: Arc<SharedState>, main_module: &ModuleSpecifier, permissions: PermissionsContainer, wait_for_inspector: bool) -> MainWorker {
+    create_main_worker_with(shared, main_module, permissions, wait_for_inspector, vec![], Default::default())
+}
+
+/// Creates the main worker with extensions installed in addition to the runtime's own and the given stdio
+fn create_main_worker_with(shared: Arc<SharedState>, main_module: &ModuleSpecifier, permissions: PermissionsContainer, wait_for_inspector: bool, extensions: Vec<Extension>, stdio: Stdio) -> MainWorker {
+    let unstable_api_guard = UnstableApiGuard::default();
     let options = WorkerOptions {
         bootstrap: BootstrapOptions {
             args: vec![shared.args0.clone(), ..shared.args.clone()],
@@ -284,7 +575,7 @@ pub async fn run(input: ScriptSource, args: Vec<String>, macros: Vec<String>, in
 
             ..Default::default()
         },
-        extensions: vec![],
+        extensions,
         startup_snapshot: Some(STARTUP_SNAPSHOT),
         skip_op_registration: false,
         create_params: None,
@@ -292,43 +583,49 @@ pub async fn run(input: ScriptSource, args: Vec<String>, macros: Vec<String>, in
         root_cert_store_provider: shared.root_cert_store_provider.clone(),
         seed: shared.seed,
         fs: Arc::new(deno_runtime::deno_fs::RealFs),
-        module_loader: Rc::new(SJSModuleLoader {file_fetcher: shared.file_fetcher.clone(), macros: shared.macros.clone(), include_paths: shared.include_paths.clone(), import_map: shared.import_map.clone()}),
+        module_loader: Rc::new(create_module_loader(&shared, root_permissions(), permissions.clone())),
+        custom_module_evaluation_cb: Some(Box::new(util::evaluate_raw_module)),
         node_resolver: None,
         npm_resolver: None,
         create_web_worker_cb: create_web_worker_callback(shared.clone()),
         format_js_error_fn: Some(Arc::new(deno_runtime::fmt_errors::format_js_error)),
-        source_map_getter: None, // Source maps not implemented, may change in future
+        source_map_getter: Some(Rc::new(shared.source_maps.clone())),
         maybe_inspector_server: shared.inspector.clone(),
         should_break_on_first_statement: false,
-        should_wait_for_inspector_session: inspector_options.wait,
+        should_wait_for_inspector_session: wait_for_inspector,
         strace_ops: None,
         get_error_class_fn: Some(&(|e| deno_runtime::errors::get_error_class_name(e).unwrap_or("Error"))),
-        cache_storage_dir: Some(get_temp_directory().join(util::hash(main_module.to_string().as_bytes()))),
+        cache_storage_dir: Some(shared.temp_dir.join(util::hash(main_module.to_string().as_bytes()))),
         
-        origin_storage_dir: Some(get_storage_directory().unwrap_or_else(|| get_temp_directory()).join(util::hash(main_module.to_string().as_bytes()))),
+        origin_storage_dir: Some(shared.storage_dir.join(util::hash(main_module.to_string().as_bytes()))),
 
         blob_store: shared.blob_store.clone(),
         broadcast_channel: shared.broadcast_channel.clone(),
         shared_array_buffer_store: shared.shared_array_buffer_store.clone(),
         compiled_wasm_module_store: shared.compiled_wasm_module_store.clone(),
-        stdio: Default::default(),
-        feature_checker: create_feature_checker(&shared.unstable_features),
-        v8_code_cache: None // TODO implement source code cache? Option<Arc<dyn CodeCache>>,
+        stdio,
+        feature_checker: create_feature_checker(&shared.unstable_features, unstable_api_guard.clone()),
+        v8_code_cache: shared.code_cache.clone().map(|code_cache| code_cache as Arc<dyn CodeCache>),
     };
 
     let mut worker = MainWorker::bootstrap_from_options(
         main_module.clone(),
-        PermissionsContainer::allow_all(),
+        permissions,
         options
     );
+    unstable_api_guard.watch(&mut worker.js_runtime);
+    worker
+}
 
-    worker.js_runtime.maybe_init_inspector();
-    worker.execute_main_module(&main_module).await?;
-    worker.run_event_loop(false).await?;
+/// Evaluates the `node:` module that exposes `process`, `Buffer` and `global`, when enabled
+async fn preload_node_globals(worker: &mut MainWorker, enabled: bool) -> Result<(), AnyError> {
+    if enabled {
+        worker.execute_side_module(&ModuleSpecifier::parse(util::node::GLOBALS_SPECIFIER).unwrap()).await?;
+    }
     Ok(())
 }
 
-async fn create_import_map(file_fetcher: Arc<FileFetcher>, main_module: &ModuleSpecifier, maybe_import_map_source: Option<String>, expand_imports: bool) -> Option<ImportMap> {
+async fn create_import_map(file_fetcher: Arc<FileFetcher>, main_module: &ModuleSpecifier, maybe_import_map_source: Option<String>, expand_imports: bool) -> Result<Option<ImportMap>, SjsError> {
     async fn load(file_fetcher: Arc<FileFetcher>, main_module: &ModuleSpecifier, maybe_import_map_source: Option<String>, expand_imports: bool) -> Result<ImportMap,AnyError> {
         let specifier = match maybe_import_map_source {
             Some(import_map_source) => resolve_maybe_url(import_map_source)?,
@@ -345,11 +642,12 @@ async fn create_import_map(file_fetcher: Arc<FileFetcher>, main_module: &ModuleS
             }
         };
 
-        let text = file_fetcher.fetch(&specifier, PermissionsContainer::allow_all()).await.map_err(|x| generic_error(format!("{}: {}",specifier,x)))?.source.clone();
+        let file = file_fetcher.fetch(&specifier, root_permissions()).await.map_err(|x| generic_error(format!("{}: {}",specifier,x)))?;
 
         return Ok(import_map::parse_from_json_with_options(
             &specifier,
-            std::str::from_utf8(&text)?,
+            // Comments and trailing commas are blanked out in place, so errors point into the original file
+            &util::jsonc::strip_jsonc(&file.into_text_decoded()?.source),
             import_map::ImportMapOptions {
                 expand_imports,
                 ..Default::default()
@@ -357,9 +655,9 @@ async fn create_import_map(file_fetcher: Arc<FileFetcher>, main_module: &ModuleS
         )?.import_map);
     }
 
-    return match load(file_fetcher, main_module, maybe_import_map_source.clone(), expand_imports).await {
-        Ok(imports) => Some(imports),
-        Err(_) if maybe_import_map_source.is_none() => None,
-        Err(x) => panic!("{}: {}", maybe_import_map_source.unwrap(), x)
+    return match (load(file_fetcher, main_module, maybe_import_map_source.clone(), expand_imports).await, maybe_import_map_source) {
+        (Ok(imports), _) => Ok(Some(imports)),
+        (Err(_), None) => Ok(None),
+        (Err(error), Some(specifier)) => Err(SjsError::ImportMap { specifier, error })
     };
 }
@@ -1,18 +1,148 @@
 use backtrace::Backtrace;
 
-use clap::{Arg, Command, ArgAction};
+use clap::{Arg, ArgMatches, Command, ArgAction};
 
 use sjs::ScriptSource;
 use sjs::InspectorOptions;
+use sjs::RuntimeOptions;
+use sjs::CacheSetting;
+use sjs::Config;
+use sjs::PermissionsOptions;
+use sjs::WatchOptions;
 
 use or_panic::OrPanic;
 
 use std::panic;
 use std::io;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
-    let matches = Command::new("SJS")
+    // A damaged binary must not fall back to the CLI, which would read the program's arguments as its own
+    set_panic_hook(false);
+    if let Some(standalone) = sjs::load_standalone().or_panic() {
+        sjs::run_standalone(standalone, std::env::args().skip(1).collect()).await.or_panic();
+        return;
+    }
+
+    let matches = command().get_matches();
+
+    // Builtin commands see global options given before and after the command name
+    let global_matches = match matches.subcommand() {
+        Some(("test" | "bundle" | "compile" | "cache" | "info" | "vendor", command_matches)) => command_matches,
+        _ => &matches
+    };
+    
+    set_panic_hook(global_matches.get_flag("verbose"));
+
+    if matches.get_flag("clear-cache") {
+        if let Some(path) = sjs::get_storage_directory().map(|x| x.join("libs")) {
+            std::fs::remove_dir_all(path.clone()).map_err(|x| format!("{}: {}", path.display(), x)).or_panic();
+        }
+        if matches.subcommand() == None {
+            return;
+        }
+    };
+
+    // Project defaults are found by walking up from the entry point
+    let entry = match matches.subcommand() {
+        Some(("test", command_matches)) => command_matches.get_many::<String>("paths").and_then(|mut x| x.next()).cloned(),
+        Some(("cache", command_matches)) => command_matches.get_many::<String>("entries").and_then(|mut x| x.next()).cloned(),
+        Some(("bundle" | "compile" | "info" | "vendor", command_matches)) => command_matches.get_one::<String>("entry").cloned(),
+        Some(("-", _)) | None => None,
+        Some((input, _)) => Some(input.to_string())
+    };
+    let config = sjs::Config::find(entry.as_deref()).or_panic();
+
+    let options = get_runtime_options(global_matches, config.as_ref());
+
+    if let Some(("test", command_matches)) = matches.subcommand() {
+        let paths = command_matches.get_many::<String>("paths").map(|x| x.cloned().collect()).unwrap_or(vec![]);
+        let passed = sjs::test(paths, options).await.or_panic();
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
+    if let Some(("bundle", command_matches)) = matches.subcommand() {
+        let entry = command_matches.get_one::<String>("entry").unwrap().clone();
+        let bundle = sjs::bundle(entry, options).await.or_panic();
+        match command_matches.get_one::<String>("output") {
+            Some(path) => std::fs::write(path, bundle).map_err(|x| format!("{}: {}", path, x)).or_panic(),
+            None => print!("{}", bundle)
+        }
+        return;
+    }
+
+    if let Some(("compile", command_matches)) = matches.subcommand() {
+        let entry = command_matches.get_one::<String>("entry").unwrap().clone();
+        let output = sjs::compile(entry, command_matches.get_one::<String>("output").cloned(), options).await.or_panic();
+        eprintln!("Compiled {}", output.display());
+        return;
+    }
+
+    if let Some(("cache", command_matches)) = matches.subcommand() {
+        let entries = command_matches.get_many::<String>("entries").unwrap().cloned().collect();
+        let cached = sjs::cache(entries, options).await.or_panic();
+        std::process::exit(if cached { 0 } else { 1 });
+    }
+
+    if let Some(("info", command_matches)) = matches.subcommand() {
+        let entry = command_matches.get_one::<String>("entry").unwrap().clone();
+        print!("{}", sjs::info(entry, command_matches.get_flag("json"), options).await.or_panic());
+        return;
+    }
+
+    if let Some(("vendor", command_matches)) = matches.subcommand() {
+        let entry = command_matches.get_one::<String>("entry").unwrap().clone();
+        let import_map = sjs::vendor(entry, command_matches.get_one::<String>("output").cloned(), options).await.or_panic();
+        eprintln!("Updated {}", import_map.display());
+        return;
+    }
+
+    let (source, args) = match matches.subcommand() {
+        Some(("-", args)) => {
+            (ScriptSource::Text(read_stdin()), args.get_many::<String>("").unwrap_or_default().map(|s| s.to_string()).collect())
+        }
+        Some((input, args)) => {
+            (ScriptSource::FileOrURL(input.to_string()), args.get_many::<String>("").unwrap_or_default().map(|s| s.to_string()).collect())
+        }
+        _ if io::stdin().is_terminal() => {
+            sjs::repl(options).await.or_panic();
+            return;
+        }
+        _ => {
+            (ScriptSource::Text(read_stdin()), vec![])
+        }
+    };
+
+    let config_port = config.as_ref().and_then(|config| config.port);
+    let port = match matches.get_one::<u16>("port") {
+        Some(port) => Some(port).copied(),
+        None if matches.get_flag("inspect") => Some(config_port.unwrap_or(9229)),
+        _ => config_port
+    };
+
+    let inspector_options = InspectorOptions {
+        wait: matches.get_flag("inspect"),
+        port
+    };
+
+    if matches.get_flag("watch") {
+        let watch_options = WatchOptions {
+            clear_screen: !matches.get_flag("no-clear-screen"),
+            debounce: matches.get_one::<u64>("debounce").map(|ms| Duration::from_millis(*ms)).unwrap_or(WatchOptions::default().debounce),
+            paths: config.as_ref().map(|config| config.path.clone()).into_iter().collect()
+        };
+        sjs::watch(source, args, options, inspector_options, watch_options).await.or_panic();
+        return;
+    }
+
+    sjs::run(source, args, options, inspector_options).await.or_panic();
+}
+
+fn command() -> Command {
+    Command::new("SJS")
         .version(clap::crate_version!())
         .author(clap::crate_authors!())
         .about("A simple JavaScript runtime")
@@ -22,6 +152,10 @@ async fn main() {
 {author-with-newline}{about-with-newline}
 USAGE:
 {tab}sjs [OPTIONS] [SOURCE] [ARGS...]
+{tab}sjs [OPTIONS] <COMMAND> [ARGS...]
+
+COMMANDS:
+{subcommands}
 
 OPTIONS:
 {options}
@@ -40,6 +174,7 @@ OPTIONS:
             .short('V')
             .long("verbose")
             .help("Prints verbose error messages")
+            .global(true)
             .action(ArgAction::SetTrue)
         )
 
@@ -69,18 +204,133 @@ OPTIONS:
             .action(ArgAction::Set)
         )
 
+        .arg(Arg::new("watch")
+            .short('w')
+            .long("watch")
+            .help("Restart when a loaded module, included file, the import map or sjs.json changes")
+            .action(ArgAction::SetTrue)
+        )
+
+        .arg(Arg::new("no-clear-screen")
+            .long("no-clear-screen")
+            .help("Do not clear the terminal when restarting in watch mode")
+            .requires("watch")
+            .action(ArgAction::SetTrue)
+        )
+
+        .arg(Arg::new("debounce")
+            .long("debounce")
+            .value_name("MS")
+            .help("Wait for changes to stop for this long before restarting in watch mode [default: 200]")
+            .requires("watch")
+            .num_args(1)
+            .value_parser(clap::value_parser!(u64))
+            .action(ArgAction::Set)
+        )
+
         .arg(Arg::new("remote")
             .short('r')
             .long("remote")
             .help("Allow running and importing URLs")
+            .global(true)
+            .action(ArgAction::SetTrue)
+        )
+
+        .arg(Arg::new("no-remote")
+            .long("no-remote")
+            .help("Disallow running and importing URLs even if 'sjs.json' allows it")
+            .conflicts_with("remote")
+            .global(true)
+            .action(ArgAction::SetTrue)
+        )
+
+        .arg(Arg::new("reload")
+            .long("reload")
+            .value_name("PREFIXES")
+            .help("Reload remote dependencies instead of using the cache, optionally only those matching comma separated URL prefixes")
+            .num_args(0..=1)
+            .require_equals(true)
+            .value_delimiter(',')
+            .default_missing_value("")
+            .conflicts_with_all(["cached-only", "respect-headers"])
+            .global(true)
+            .action(ArgAction::Append)
+        )
+
+        .arg(Arg::new("cached-only")
+            .long("cached-only")
+            .help("Only use cached remote dependencies, failing on any that are not cached")
+            .conflicts_with("respect-headers")
+            .global(true)
+            .action(ArgAction::SetTrue)
+        )
+
+        .arg(Arg::new("respect-headers")
+            .long("respect-headers")
+            .help("Reload cached remote dependencies that are no longer fresh according to their HTTP headers")
+            .global(true)
+            .action(ArgAction::SetTrue)
+        )
+
+        .arg(Arg::new("no-transpile-cache")
+            .long("no-transpile-cache")
+            .help("Compile every module again instead of reusing output from earlier runs")
+            .global(true)
+            .action(ArgAction::SetTrue)
+        )
+
+        .arg(Arg::new("lock")
+            .long("lock")
+            .value_name("PATH")
+            .help("Check remote modules and npm packages against this lockfile [default: 'sjs.lock' next to 'sjs.json', or in the current directory if it exists]")
+            .num_args(1)
+            .global(true)
+            .action(ArgAction::Set)
+        )
+
+        .arg(Arg::new("lock-write")
+            .long("lock-write")
+            .help("Replace the hashes in the lockfile with those of the current remote modules and npm packages")
+            .global(true)
+            .action(ArgAction::SetTrue)
+        )
+
+        .arg(Arg::new("no-lock")
+            .long("no-lock")
+            .help("Do not check remote modules against a lockfile")
+            .conflicts_with_all(["lock", "lock-write"])
+            .global(true)
+            .action(ArgAction::SetTrue)
+        )
+
+        .arg(Arg::new("allow-all")
+            .short('A')
+            .long("allow-all")
+            .help("Allow all permissions, this is the default unless another --allow-* flag is given")
+            .global(true)
             .action(ArgAction::SetTrue)
         )
+        .arg(permission_arg("allow-read", "PATH", "Allow file system read access, optionally only to the given paths"))
+        .arg(permission_arg("allow-write", "PATH", "Allow file system write access, optionally only to the given paths"))
+        .arg(permission_arg("allow-net", "HOST", "Allow network access, optionally only to the given hosts"))
+        .arg(permission_arg("allow-env", "VARIABLE", "Allow environment access, optionally only to the given variables"))
+        .arg(permission_arg("allow-run", "PROGRAM", "Allow running subprocesses, optionally only the given programs"))
+        .arg(permission_arg("allow-sys", "API", "Allow access to system information, optionally only from the given APIs"))
+        .arg(permission_arg("allow-ffi", "PATH", "Allow loading dynamic libraries, optionally only from the given paths"))
+        .arg(permission_arg("deny-read", "PATH", "Deny file system read access, optionally only to the given paths"))
+        .arg(permission_arg("deny-write", "PATH", "Deny file system write access, optionally only to the given paths"))
+        .arg(permission_arg("deny-net", "HOST", "Deny network access, optionally only to the given hosts"))
+        .arg(permission_arg("deny-env", "VARIABLE", "Deny environment access, optionally only to the given variables"))
+        .arg(permission_arg("deny-run", "PROGRAM", "Deny running subprocesses, optionally only the given programs"))
+        .arg(permission_arg("deny-sys", "API", "Deny access to system information, optionally only from the given APIs"))
+        .arg(permission_arg("deny-ffi", "PATH", "Deny loading dynamic libraries, optionally only from the given paths"))
 
         .arg(Arg::new("macros")
             .short('D')
             .long("define")
             .value_name("MACROS")
             .help("Define macros using the form 'MACRO(x)=definition'")
+            .global(true)
             .action(ArgAction::Append)
         )
 
@@ -88,26 +338,140 @@ OPTIONS:
             .short('I')
             .value_name("PATH")
             .help("Add additional include search paths")
+            .global(true)
             .action(ArgAction::Append)
         )
 
+        .arg(Arg::new("npm-registry")
+            .long("npm-registry")
+            .help("Download npm: packages from this registry (By default uses $NPM_CONFIG_REGISTRY or registry.npmjs.org)")
+            .value_name("URL")
+            .num_args(1)
+            .global(true)
+            .action(ArgAction::Set)
+        )
+
+        .arg(Arg::new("node-globals")
+            .long("node-globals")
+            .help("Expose Node's process, Buffer and global globals")
+            .global(true)
+            .action(ArgAction::SetTrue)
+        )
+
+        .arg(Arg::new("no-node-globals")
+            .long("no-node-globals")
+            .help("Do not expose Node's globals even if 'sjs.json' enables them")
+            .conflicts_with("node-globals")
+            .global(true)
+            .action(ArgAction::SetTrue)
+        )
+
         .arg(Arg::new("import-map")
             .short('m')
             .long("import-map")
             .help("Load a JSON formatted import map (By default tries to load 'imports.json')")
             .value_name("PATH")
             .num_args(1)
+            .global(true)
             .action(ArgAction::Set)
         )
 
+        .subcommand(Command::new("test")
+            .about("Run tests registered with system.test() (By default searches the current directory)")
+            .arg(Arg::new("paths")
+                .value_name("PATHS")
+                .help("Test files or directories to search for '*.test.*' and '*_test.*' files")
+                .num_args(0..)
+                .action(ArgAction::Append)
+            )
+        )
+        .subcommand(Command::new("bundle")
+            .about("Bundle a module and its static imports into a single JavaScript file")
+            .long_about("Bundle a module and its static imports into a single JavaScript file\n\n\
+                Imported bindings are copied when the importing module starts, so they do not see later reassignments \
+                by the exporting module, and circular imports are not supported. Local files that are only imported \
+                dynamically cannot be bundled.")
+            .arg(Arg::new("entry")
+                .value_name("SOURCE")
+                .help("The entry point file or URL")
+                .required(true)
+                .action(ArgAction::Set)
+            )
+            .arg(Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("PATH")
+                .help("Write the bundle to a file instead of stdout")
+                .num_args(1)
+                .action(ArgAction::Set)
+            )
+        )
+        .subcommand(Command::new("compile")
+            .about("Compile a module and its dependencies into a standalone executable (npm: packages are not supported)")
+            .arg(Arg::new("entry")
+                .value_name("SOURCE")
+                .help("The entry point file or URL")
+                .required(true)
+                .action(ArgAction::Set)
+            )
+            .arg(Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("PATH")
+                .help("The executable to create (By default uses the entry point's name)")
+                .num_args(1)
+                .action(ArgAction::Set)
+            )
+        )
+        .subcommand(Command::new("cache")
+            .about("Download the remote dependencies of modules without running them")
+            .arg(Arg::new("entries")
+                .value_name("SOURCE")
+                .help("The entry point files or URLs")
+                .required(true)
+                .num_args(1..)
+                .action(ArgAction::Append)
+            )
+        )
+        .subcommand(Command::new("info")
+            .about("Show the dependency tree of a module and where its dependencies are cached")
+            .arg(Arg::new("entry")
+                .value_name("SOURCE")
+                .help("The entry point file or URL")
+                .required(true)
+                .action(ArgAction::Set)
+            )
+            .arg(Arg::new("json")
+                .long("json")
+                .help("Output the module graph as JSON")
+                .action(ArgAction::SetTrue)
+            )
+        )
+        .subcommand(Command::new("vendor")
+            .about("Copy the remote dependencies of a module into the project and map them in the import map (comments in a local import map are not kept)")
+            .arg(Arg::new("entry")
+                .value_name("SOURCE")
+                .help("The entry point file or URL")
+                .required(true)
+                .action(ArgAction::Set)
+            )
+            .arg(Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("DIR")
+                .help("The directory to copy dependencies to (By default 'vendor' next to the import map)")
+                .num_args(1)
+                .action(ArgAction::Set)
+            )
+        )
+        .disable_help_subcommand(true)
+
         .external_subcommand_value_parser(clap::value_parser!(String))
         .allow_external_subcommands(true)
         .subcommand_value_name("SOURCE")
+}
 
-        .get_matches();
-
-    
-    let verbose = matches.get_flag("verbose");
+fn set_panic_hook(verbose: bool) {
     panic::set_hook(Box::new(move |info| {
         eprintln!("\x1b[91;1merror\x1b[0m: {}", panic_message::panic_info_message(info));
         
@@ -119,45 +483,176 @@ OPTIONS:
 
         std::process::exit(-1);
     }));
+}
 
-    if matches.get_flag("clear-cache") {
-        if let Some(path) = sjs::get_storage_directory().map(|x| x.join("libs")) {
-            std::fs::remove_dir_all(path.clone()).map_err(|x| format!("{}: {}", path.display(), x)).or_panic();
-        }
-        if matches.subcommand() == None {
-            return;
-        }
-    };
+/// Flags take precedence over the config file, macros defined by flags come after (and so redefine) the
+/// config's and include paths given by flags are searched first
+fn get_runtime_options(matches: &ArgMatches, config: Option<&Config>) -> RuntimeOptions {
+    let config = config.cloned().unwrap_or_default();
+    let cache_setting = get_cache_setting(matches).or(config.cache_setting()).unwrap_or_default();
+    let lockfile = get_lockfile(matches, &config);
+    RuntimeOptions {
+        macros: [config.macros, matches.get_many::<String>("macros").map(|x| x.cloned().collect()).unwrap_or(vec![])].concat(),
+        include_paths: [matches.get_many::<String>("include-paths").map(|x| x.cloned().collect()).unwrap_or(vec![]), config.include_paths].concat(),
+        allow_remote: get_switch(matches, "remote", config.remote),
+        import_map_source: matches.get_one::<String>("import-map").map(|s| s.clone()).or(config.import_map),
+        cache_setting,
+        permissions: get_permissions_options(matches),
+        unstable_features: config.unstable,
+        transpile_cache: !matches.get_flag("no-transpile-cache"),
+        lockfile,
+        lock_write: matches.get_flag("lock-write"),
+        npm_registry: matches.get_one::<String>("npm-registry").cloned().or(config.npm_registry),
+        node_globals: get_switch(matches, "node-globals", config.node_globals),
 
-    let (source, args) = match matches.subcommand() {
-        Some(("-", args)) => {
-            (ScriptSource::Text(read_stdin()), args.get_many::<String>("").unwrap_or_default().map(|s| s.to_string()).collect())
-        }
-        Some((input, args)) => {
-            (ScriptSource::FileOrURL(input.to_string()), args.get_many::<String>("").unwrap_or_default().map(|s| s.to_string()).collect())
-        }
-        _ => {
-            (ScriptSource::Text(read_stdin()), vec![])
+        ..Default::default()
+    }
+}
+
+/// A flag or its `--no-` counterpart overrides the config's value
+fn get_switch(matches: &ArgMatches, id: &str, config_value: Option<bool>) -> bool {
+    if matches.get_flag(&format!("no-{id}")) {
+        false
+    } else if matches.get_flag(id) {
+        true
+    } else {
+        config_value.unwrap_or(false)
+    }
+}
+
+fn get_lockfile(matches: &ArgMatches, config: &Config) -> Option<PathBuf> {
+    if matches.get_flag("no-lock") {
+        None
+    } else if let Some(path) = matches.get_one::<String>("lock") {
+        Some(PathBuf::from(path))
+    } else if config.path.as_os_str().is_empty() {
+        // Outside of a project only an existing lockfile is used, unless asked to write one
+        Some(PathBuf::from(sjs::LOCKFILE_NAME)).filter(|path| matches.get_flag("lock-write") || path.is_file())
+    } else {
+        Some(config.path.with_file_name(sjs::LOCKFILE_NAME))
+    }
+}
+
+fn permission_arg(id: &'static str, value_name: &'static str, help: &'static str) -> Arg {
+    Arg::new(id)
+        .long(id)
+        .value_name(value_name)
+        .help(help)
+        .num_args(0..=1)
+        .require_equals(true)
+        .value_delimiter(',')
+        .default_missing_value("")
+        .global(true)
+        .action(ArgAction::Append)
+}
+
+fn get_permissions_options(matches: &ArgMatches) -> PermissionsOptions {
+    fn get_list(matches: &ArgMatches, id: &str) -> Option<Vec<String>> {
+        matches.get_many::<String>(id).map(|x| x.filter(|x| !x.is_empty()).cloned().collect())
+    }
+
+    fn get_paths(matches: &ArgMatches, id: &str) -> Option<Vec<PathBuf>> {
+        get_list(matches, id).map(|x| x.into_iter().map(PathBuf::from).collect())
+    }
+
+    // Permissions are only restricted once something is explicitly allowed
+    let restricted = !matches.get_flag("allow-all") && ["allow-read", "allow-write", "allow-net", "allow-env", "allow-run", "allow-sys", "allow-ffi"].iter().any(|id| matches.contains_id(id));
+
+    let allowed = if restricted {
+        PermissionsOptions {
+            allow_env: get_list(matches, "allow-env"),
+            allow_hrtime: true,
+            allow_net: get_list(matches, "allow-net"),
+            allow_ffi: get_paths(matches, "allow-ffi"),
+            allow_read: get_paths(matches, "allow-read"),
+            allow_run: get_list(matches, "allow-run"),
+            allow_sys: get_list(matches, "allow-sys"),
+            allow_write: get_paths(matches, "allow-write"),
+            prompt: false,
+
+            ..Default::default()
         }
+    } else {
+        sjs::allow_all_permissions()
     };
 
-    let port = match matches.get_one::<u16>("port") {
-        Some(port) => Some(port).copied(),
-        None if matches.get_flag("inspect") => Some(9229),
-        _ => None
-    };
+    PermissionsOptions {
+        deny_env: get_list(matches, "deny-env"),
+        deny_net: get_list(matches, "deny-net"),
+        deny_ffi: get_paths(matches, "deny-ffi"),
+        deny_read: get_paths(matches, "deny-read"),
+        deny_run: get_list(matches, "deny-run"),
+        deny_sys: get_list(matches, "deny-sys"),
+        deny_write: get_paths(matches, "deny-write"),
 
-    let import_map_source = matches.get_one::<String>("import-map").map(|s| s.clone());
-    
-    let macros = matches.get_many::<String>("macros").map(|x| x.cloned().collect()).unwrap_or(vec![]);
-    let include_paths = matches.get_many::<String>("include-paths").map(|x| x.cloned().collect()).unwrap_or(vec![]);
+        ..allowed
+    }
+}
 
-    sjs::run(source, args, macros, include_paths, matches.get_flag("remote"), import_map_source, InspectorOptions {
-        wait: matches.get_flag("inspect"),
-        port
-    }).await.or_panic();
+fn get_cache_setting(matches: &ArgMatches) -> Option<CacheSetting> {
+    if let Some(prefixes) = matches.get_many::<String>("reload") {
+        let prefixes: Vec<String> = prefixes.filter(|x| !x.is_empty()).map(|x| x.trim_end_matches('/').to_string()).collect();
+        if prefixes.is_empty() {
+            Some(CacheSetting::ReloadAll)
+        } else {
+            Some(CacheSetting::ReloadSome(prefixes))
+        }
+    } else if matches.get_flag("cached-only") {
+        Some(CacheSetting::Only)
+    } else if matches.get_flag("respect-headers") {
+        Some(CacheSetting::RespectHeaders)
+    } else {
+        None
+    }
 }
 
 fn read_stdin() -> String {
     return io::read_to_string(io::stdin()).expect("Error reading stdin")
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(args: &[&str]) -> ArgMatches {
+        command().try_get_matches_from([&["sjs"], args].concat()).unwrap()
+    }
+
+    #[test]
+    fn cache_flags() {
+        assert_eq!(get_cache_setting(&matches(&["main.ts"])), None);
+        assert_eq!(get_cache_setting(&matches(&["--reload", "main.ts"])), Some(CacheSetting::ReloadAll));
+        assert_eq!(
+            get_cache_setting(&matches(&["--reload=https://example.com/,https://deno.land/std", "main.ts"])),
+            Some(CacheSetting::ReloadSome(vec![String::from("https://example.com"), String::from("https://deno.land/std")]))
+        );
+        assert_eq!(get_cache_setting(&matches(&["--cached-only", "main.ts"])), Some(CacheSetting::Only));
+        assert_eq!(get_cache_setting(&matches(&["--respect-headers", "main.ts"])), Some(CacheSetting::RespectHeaders));
+        assert!(command().try_get_matches_from(["sjs", "--reload", "--cached-only", "main.ts"]).is_err());
+    }
+
+    #[test]
+    fn allows_all_by_default() {
+        let options = get_permissions_options(&matches(&["--deny-net=example.com", "main.ts"]));
+        assert_eq!(options.allow_read, Some(vec![]));
+        assert_eq!(options.allow_net, Some(vec![]));
+        assert_eq!(options.deny_net, Some(vec![String::from("example.com")]));
+    }
+
+    #[test]
+    fn allow_flags_restrict_permissions() {
+        let options = get_permissions_options(&matches(&["--allow-read=/tmp,/var", "--allow-net", "main.ts"]));
+        assert_eq!(options.allow_read, Some(vec![PathBuf::from("/tmp"), PathBuf::from("/var")]));
+        assert_eq!(options.allow_net, Some(vec![]));
+        assert_eq!(options.allow_write, None);
+        assert_eq!(options.allow_env, None);
+        assert_eq!(options.deny_read, None);
+    }
+
+    #[test]
+    fn allow_all_overrides_allow_flags() {
+        let options = get_permissions_options(&matches(&["--allow-all", "--allow-read=/tmp", "main.ts"]));
+        assert_eq!(options.allow_read, Some(vec![]));
+        assert_eq!(options.allow_write, Some(vec![]));
+    }
+}
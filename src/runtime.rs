@@ -0,0 +1,245 @@
+use deno_runtime::deno_core;
+use deno_core::{Extension,ModuleSpecifier};
+use deno_core::error::AnyError;
+use deno_runtime::deno_io::Stdio;
+use deno_runtime::deno_permissions::PermissionsOptions;
+use deno_runtime::deno_tls::RootCertStoreProvider;
+use deno_runtime::inspector_server::InspectorServer;
+use deno_runtime::worker::MainWorker;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::{CacheSetting,InspectorOptions,RuntimeOptions,ScriptSource};
+use crate::util::watch::WatchedFiles;
+
+/// Creates a new instance of an extension for each worker it is installed into
+pub type ExtensionFactory = Arc<dyn Fn() -> Extension + Send + Sync>;
+
+/// What embedders install into workers besides the runtime itself
+#[derive(Default)]
+pub(crate) struct Embedding {
+    /// Only installed into the main worker
+    pub extensions: Vec<Extension>,
+    /// Installed into the main worker and every web worker
+    pub extension_factories: Vec<ExtensionFactory>,
+    /// The main worker's stdio, web workers always use the process's
+    pub stdio: Stdio,
+}
+
+/// Configures a program to run for applications that embed sjs, `build` creates the `Runtime` that runs it
+///
+/// Custom ops are added with `deno_core::Extension`s. The startup snapshot only contains the runtime's own
+/// extensions, so the ES modules of custom extensions are evaluated as each worker starts. These modules run
+/// before the `system` global exists, and anything they set on `globalThis[Symbol.for("sjs.system")]` is
+/// copied onto `system` afterwards:
+///
+/// ```text
+/// // The extension's `esm_entry_point`
+/// import { op_greet } from "ext:core/ops";
+/// globalThis[Symbol.for("sjs.system")] ??= {};
+/// globalThis[Symbol.for("sjs.system")].greet = (name) => op_greet(name);
+/// ```
+///
+/// Scripts can then call `system.greet("world")`.
+pub struct RuntimeBuilder {
+    input: ScriptSource,
+    args: Vec<String>,
+    options: RuntimeOptions,
+    inspector_options: InspectorOptions,
+    embedding: Embedding,
+}
+
+impl RuntimeBuilder {
+    pub fn new(input: ScriptSource) -> Self {
+        Self {
+            input,
+            args: vec![],
+            options: Default::default(),
+            inspector_options: InspectorOptions {
+                port: None,
+                wait: false
+            },
+            embedding: Default::default(),
+        }
+    }
+
+    /// The arguments the program sees after its own name
+    pub fn args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Replaces every option, later calls to the other setters change the given options
+    pub fn options(mut self, options: RuntimeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn macros(mut self, macros: Vec<String>) -> Self {
+        self.options.macros = macros;
+        self
+    }
+
+    pub fn include_paths(mut self, include_paths: Vec<String>) -> Self {
+        self.options.include_paths = include_paths;
+        self
+    }
+
+    pub fn allow_remote(mut self, allow_remote: bool) -> Self {
+        self.options.allow_remote = allow_remote;
+        self
+    }
+
+    /// A file path or URL, by default `imports.json` next to the main module is used if it exists
+    pub fn import_map(mut self, import_map_source: impl Into<String>) -> Self {
+        self.options.import_map_source = Some(import_map_source.into());
+        self
+    }
+
+    pub fn cache_setting(mut self, cache_setting: CacheSetting) -> Self {
+        self.options.cache_setting = cache_setting;
+        self
+    }
+
+    /// By default every permission is granted, see `allow_all_permissions`
+    pub fn permissions(mut self, permissions: PermissionsOptions) -> Self {
+        self.options.permissions = permissions;
+        self
+    }
+
+    /// Names from `deno_runtime::UNSTABLE_GRANULAR_FLAGS` to enable in addition to the defaults
+    pub fn unstable_features(mut self, unstable_features: Vec<String>) -> Self {
+        self.options.unstable_features = unstable_features;
+        self
+    }
+
+    /// Exposes Node's `process`, `Buffer` and `global` before the main module runs
+    pub fn node_globals(mut self, node_globals: bool) -> Self {
+        self.options.node_globals = node_globals;
+        self
+    }
+
+    pub fn root_cert_store_provider(mut self, root_cert_store_provider: Arc<dyn RootCertStoreProvider>) -> Self {
+        self.options.root_cert_store_provider = Some(root_cert_store_provider);
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.options.seed = Some(seed);
+        self
+    }
+
+    /// Where remote modules, npm packages and caches are kept instead of `~/.sjs`
+    pub fn storage_dir(mut self, storage_dir: impl Into<PathBuf>) -> Self {
+        self.options.storage_dir = Some(storage_dir.into());
+        self
+    }
+
+    /// Where per-program caches are kept instead of `$TMPDIR/sjs`
+    pub fn temp_dir(mut self, temp_dir: impl Into<PathBuf>) -> Self {
+        self.options.temp_dir = Some(temp_dir.into());
+        self
+    }
+
+    /// The main worker's stdin, stdout and stderr, by default those of the process
+    pub fn stdio(mut self, stdio: Stdio) -> Self {
+        self.embedding.stdio = stdio;
+        self
+    }
+
+    /// Installs an extension into the main worker in addition to the runtime's own
+    pub fn extension(mut self, extension: Extension) -> Self {
+        self.embedding.extensions.push(extension);
+        self
+    }
+
+    /// Installs an extension into the main worker and every web worker it creates, an extension can only
+    /// be installed once so `factory` creates one for each worker
+    pub fn extension_factory(mut self, factory: impl Fn() -> Extension + Send + Sync + 'static) -> Self {
+        self.embedding.extension_factories.push(Arc::new(factory));
+        self
+    }
+
+    pub fn inspector(mut self, inspector_options: InspectorOptions) -> Self {
+        self.inspector_options = inspector_options;
+        self
+    }
+
+    /// Loads the import map and creates the main worker without running anything yet
+    pub async fn build(self) -> Result<Runtime, AnyError> {
+        crate::init_v8();
+
+        let args0 = crate::get_args0(&self.input)?;
+        let inspector = crate::create_inspector_server(&self.inspector_options)?;
+        create_runtime(self.input, args0, self.args, &self.options, inspector, self.inspector_options.wait, None, self.embedding).await
+    }
+}
+
+/// A program ready to run, see `RuntimeBuilder`
+pub struct Runtime {
+    worker: MainWorker,
+    main_module: ModuleSpecifier,
+    node_globals: bool,
+}
+
+impl Runtime {
+    pub fn main_module(&self) -> &ModuleSpecifier {
+        &self.main_module
+    }
+
+    /// The underlying worker, for evaluating scripts or driving the event loop directly
+    pub fn worker(&mut self) -> &mut MainWorker {
+        &mut self.worker
+    }
+
+    /// Runs the main module and then the event loop until it finishes, returns the exit code the program set
+    pub async fn run(&mut self) -> Result<i32, AnyError> {
+        match self.run_main_module().await {
+            Ok(()) => Ok(self.worker.exit_code()),
+            Err(err) => Err(crate::unstable_api_error(&mut self.worker.js_runtime).unwrap_or(err))
+        }
+    }
+
+    async fn run_main_module(&mut self) -> Result<(), AnyError> {
+        crate::preload_node_globals(&mut self.worker, self.node_globals).await?;
+        self.worker.execute_main_module(&self.main_module).await?;
+        self.worker.run_event_loop(false).await?;
+        Ok(())
+    }
+}
+
+/// Creates the main worker for a program, the local files it loads are sent to `watched_files` if given
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn create_runtime(input: ScriptSource, args0: String, args: Vec<String>, options: &RuntimeOptions, inspector: Option<Arc<InspectorServer>>, wait_for_inspector: bool, watched_files: Option<WatchedFiles>, embedding: Embedding) -> Result<Runtime, AnyError> {
+    let file_fetcher = crate::create_file_fetcher(options)?;
+    let main_module = crate::resolve_main_module(input, &file_fetcher)?;
+
+    let permissions = crate::create_permissions(options)?;
+    let mut shared = crate::create_shared_state(&main_module, args0, args, file_fetcher, options, inspector).await?;
+
+    if let Some(watched_files) = watched_files {
+        if let Some(path) = shared.import_map.as_ref().and_then(|import_map| import_map.base_url().to_file_path().ok()) {
+            watched_files.add(path);
+        }
+        Arc::make_mut(&mut shared).watched_files = Some(watched_files);
+    }
+
+    let extensions: Vec<Extension> = embedding.extension_factories.iter().map(|factory| factory())
+        .chain(embedding.extensions)
+        .collect();
+    let expose_extensions = !extensions.is_empty();
+    Arc::make_mut(&mut shared).extension_factories = embedding.extension_factories;
+
+    let mut worker = crate::create_main_worker_with(shared, &main_module, permissions, wait_for_inspector, extensions, embedding.stdio);
+    if expose_extensions {
+        worker.execute_script("[sjs:extensions]", crate::EXPOSE_EXTENSIONS.to_string().into())?;
+    }
+    worker.js_runtime.maybe_init_inspector();
+
+    Ok(Runtime {
+        worker,
+        main_module,
+        node_globals: options.node_globals
+    })
+}
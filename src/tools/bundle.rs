@@ -0,0 +1,441 @@
+use deno_runtime::deno_core;
+use deno_core::{ModuleSpecifier,ModuleType};
+use deno_core::error::{generic_error,AnyError};
+use deno_core::serde_json;
+
+use deno_ast::{SourcePos,SourceRange,StartSourcePos,SourceRangedForSpanned as _};
+use deno_ast::swc::ast::{
+    CallExpr,Decl,DefaultDecl,ExportSpecifier,ImportSpecifier,MetaPropExpr,MetaPropKind,
+    ModuleDecl,ModuleExportName,ModuleItem,ObjectPatProp,Pat,Program,Str
+};
+use deno_ast::swc::visit::{Visit,VisitWith};
+
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::RuntimeOptions;
+use crate::util::module_graph::{self,DependencyKind,GraphModule,ModuleGraph};
+use crate::util::url::resolve_maybe_url;
+
+/// Helpers shared by every bundled module
+const PRELUDE: &str = r#"const __sjs_namespace = () => Object.create(null, { [Symbol.toStringTag]: { value: "Module" } });
+const __sjs_reexport = (target, source) => {
+    for (const key of Object.keys(source)) {
+        if (key !== "default" && !Object.hasOwn(target, key)) {
+            Object.defineProperty(target, key, { get: () => source[key], enumerable: true });
+        }
+    }
+};
+const __sjs_decode = (data) => Uint8Array.from(atob(data), (c) => c.charCodeAt(0));
+const __sjs_resolve = (specifier, referrer) => {
+    let url = null;
+    try {
+        url = /^\.{0,2}\//.test(specifier) ? new URL(specifier, referrer).href : new URL(specifier).href;
+    } catch {}
+    const key = url ?? specifier;
+    const lookup = (map) => {
+        const prefix = Object.keys(map ?? {})
+            .filter((prefix) => prefix === key || (prefix.endsWith("/") && key.startsWith(prefix)))
+            .sort((a, b) => b.length - a.length)[0];
+        if (prefix === undefined) {
+            return undefined;
+        }
+        if (map[prefix] === null) {
+            throw new TypeError(`Import of "${specifier}" is blocked by the import map`);
+        }
+        return prefix === key ? map[prefix] : new URL(key.slice(prefix.length), map[prefix]).href;
+    };
+    const scopes = Object.keys(__sjs_import_map.scopes ?? {})
+        .filter((scope) => scope === referrer || (scope.endsWith("/") && referrer.startsWith(scope)))
+        .sort((a, b) => b.length - a.length);
+    for (const scope of scopes) {
+        const resolved = lookup(__sjs_import_map.scopes[scope]);
+        if (resolved !== undefined) {
+            return resolved;
+        }
+    }
+    return lookup(__sjs_import_map.imports) ?? url ?? new URL(specifier, referrer).href;
+};
+"#;
+
+/// What a bundled module exports, used to re-export the entry point's bindings from the bundle
+#[derive(Default)]
+struct ModuleExports {
+    names: Vec<String>,
+    star: Vec<usize>
+}
+
+/// Walks the static module graph from `entry` and inlines every module into a single ES module
+pub async fn bundle(entry: String, options: RuntimeOptions) -> Result<String, AnyError> {
+    crate::init_v8();
+
+    let main_module = resolve_maybe_url(&entry)?;
+    let loader = crate::create_tool_loader(&main_module, &options).await?;
+    let graph = ModuleGraph::build(&loader, vec![main_module.clone()], false).await;
+
+    let order = sort_modules(&graph, &main_module)?;
+    let ids: HashMap<usize, usize> = order.iter().enumerate().map(|(id, &index)| (index, id)).collect();
+
+    let mut bundle = format!("// Bundled by sjs {} from {}\n{}", crate::version(), main_module, PRELUDE);
+    // import.meta.resolve runs after bundling, so it needs its own copy of the import map
+    bundle.push_str(&format!("const __sjs_import_map = {};\n", loader.import_map.as_ref().map_or_else(|| String::from("{}"), |import_map| import_map.to_json())));
+    bundle.push_str(&format!("const {};\n", (0..order.len()).map(|id| format!("__sjs_{id} = __sjs_namespace()")).collect::<Vec<_>>().join(", ")));
+
+    let mut exports = vec![];
+    for (id, &index) in order.iter().enumerate() {
+        let graph_module = &graph.modules[index];
+        let module = graph_module.module.as_ref().map_err(|x| generic_error(x.clone()))?;
+        let resolve = |specifier: &str, kind: DependencyKind| -> Option<(ModuleSpecifier, Option<usize>)> {
+            let dependency = graph_module.dependencies.iter().find(|dependency| dependency.kind == kind && dependency.specifier == specifier)?;
+            let resolved = dependency.resolved.as_ref().ok()?;
+            Some((resolved.clone(), graph.index_of(resolved).and_then(|index| ids.get(&index).copied())))
+        };
+
+        bundle.push_str(&format!("\n// {}\n", graph_module.specifier));
+        let module_exports = match (&module.module_type, &module.code) {
+            (ModuleType::JavaScript, Some(code)) => {
+                let (source, module_exports) = bundle_javascript(id, &graph_module.specifier, code, graph_module.specifier == main_module, &resolve)?;
+                bundle.push_str(&source);
+                module_exports
+            },
+            (ModuleType::Json, Some(text)) => {
+                serde_json::from_str::<serde_json::Value>(text).map_err(|x| generic_error(format!("{}: {}", graph_module.specifier, x)))?;
+                bundle.push_str(&format!("Object.defineProperty(__sjs_{id}, \"default\", {{ value: JSON.parse({}), enumerable: true }});\n", serde_json::to_string(text)?));
+                ModuleExports { names: vec![String::from("default")], star: vec![] }
+            },
+            (ModuleType::Other(ty), Some(text)) if ty == "text" => {
+                bundle.push_str(&format!("Object.defineProperty(__sjs_{id}, \"default\", {{ value: {}, enumerable: true }});\n", serde_json::to_string(text)?));
+                ModuleExports { names: vec![String::from("default")], star: vec![] }
+            },
+            (ModuleType::Other(ty), _) if ty == "bytes" => {
+                bundle.push_str(&format!("Object.defineProperty(__sjs_{id}, \"default\", {{ value: Uint8Array.from(atob(\"{}\"), (c) => c.charCodeAt(0)), enumerable: true }});\n", BASE64_STANDARD.encode(&module.file.source)));
+                ModuleExports { names: vec![String::from("default")], star: vec![] }
+            },
+            (ModuleType::Wasm, _) => {
+                bundle.push_str(&bundle_wasm(id, graph_module, &module.file.source, &resolve)?);
+                ModuleExports::default()
+            },
+            (module_type, _) => return Err(generic_error(format!("{}: Bundling modules of type \"{}\" is not supported", graph_module.specifier, module_type)))
+        };
+        exports.push(module_exports);
+    }
+
+    let main_id = order.len() - 1;
+    let mut names = vec![];
+    collect_export_names(main_id, &exports, &mut vec![], &mut names);
+    if !names.is_empty() {
+        bundle.push('\n');
+        for (i, name) in names.iter().enumerate() {
+            bundle.push_str(&format!("const __sjs_export_{i} = __sjs_{main_id}[{}];\n", serde_json::to_string(name)?));
+        }
+        bundle.push_str(&format!("export {{ {} }};\n", names.iter().enumerate().map(|(i, name)| format!("__sjs_export_{i} as {}", serde_json::to_string(name).unwrap())).collect::<Vec<_>>().join(", ")));
+    }
+
+    Ok(bundle)
+}
+
+/// Orders the graph so every module comes after its static dependencies, the entry point last
+fn sort_modules(graph: &ModuleGraph, main_module: &ModuleSpecifier) -> Result<Vec<usize>, AnyError> {
+    fn visit(graph: &ModuleGraph, index: usize, visiting: &mut Vec<usize>, order: &mut Vec<usize>) -> Result<(), AnyError> {
+        if order.contains(&index) {
+            return Ok(());
+        }
+        let graph_module = &graph.modules[index];
+        if visiting.contains(&index) {
+            return Err(generic_error(format!("{}: Circular imports are not supported when bundling", graph_module.specifier)));
+        }
+        graph_module.module.as_ref().map_err(|x| generic_error(x.clone()))?;
+
+        visiting.push(index);
+        for dependency in graph_module.dependencies.iter().filter(|dependency| dependency.kind == DependencyKind::Static) {
+            let resolved = dependency.resolved.as_ref().map_err(|x| generic_error(format!("{}: {}", graph_module.specifier, x)))?;
+            let dependency_index = graph.index_of(resolved).ok_or_else(|| generic_error(format!("{}: Module not found", resolved)))?;
+            visit(graph, dependency_index, visiting, order)?;
+        }
+        visiting.pop();
+
+        order.push(index);
+        Ok(())
+    }
+
+    let mut order = vec![];
+    visit(graph, graph.index_of(main_module).unwrap(), &mut vec![], &mut order)?;
+    Ok(order)
+}
+
+fn collect_export_names(id: usize, exports: &Vec<ModuleExports>, visited: &mut Vec<usize>, names: &mut Vec<String>) {
+    if visited.contains(&id) {
+        return;
+    }
+    visited.push(id);
+    for name in &exports[id].names {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+    for &star in &exports[id].star {
+        let mut star_names = vec![];
+        collect_export_names(star, exports, visited, &mut star_names);
+        for name in star_names {
+            if name != "default" && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+}
+
+fn bundle_wasm(id: usize, graph_module: &GraphModule, bytes: &[u8], resolve: &dyn Fn(&str, DependencyKind) -> Option<(ModuleSpecifier, Option<usize>)>) -> Result<String, AnyError> {
+    let mut imports = vec![];
+    for dependency in &graph_module.dependencies {
+        let Some((_, Some(dependency_id))) = resolve(&dependency.specifier, DependencyKind::Static) else {
+            return Err(generic_error(format!("{}: Unable to bundle import of \"{}\"", graph_module.specifier, dependency.specifier)));
+        };
+        imports.push(format!("{}: __sjs_{dependency_id}", serde_json::to_string(&dependency.specifier)?));
+    }
+
+    Ok(format!(
+        "{{\n    const {{ exports }} = new WebAssembly.Instance(new WebAssembly.Module(__sjs_decode(\"{}\")), {{ {} }});\n    for (const [key, value] of Object.entries(exports)) {{\n        Object.defineProperty(__sjs_{id}, key, {{ value, enumerable: true }});\n    }}\n}}\n",
+        BASE64_STANDARD.encode(bytes),
+        imports.join(", ")
+    ))
+}
+
+fn export_name(name: &ModuleExportName) -> String {
+    match name {
+        ModuleExportName::Ident(ident) => ident.sym.to_string(),
+        ModuleExportName::Str(str) => str.value.to_string()
+    }
+}
+
+fn collect_binding_names(pat: &Pat, names: &mut Vec<String>) {
+    match pat {
+        Pat::Ident(binding) => names.push(binding.id.sym.to_string()),
+        Pat::Array(array) => for element in array.elems.iter().flatten() {
+            collect_binding_names(element, names);
+        },
+        Pat::Object(object) => for prop in &object.props {
+            match prop {
+                ObjectPatProp::KeyValue(kv) => collect_binding_names(&kv.value, names),
+                ObjectPatProp::Assign(assign) => names.push(assign.key.sym.to_string()),
+                ObjectPatProp::Rest(rest) => collect_binding_names(&rest.arg, names)
+            }
+        },
+        Pat::Rest(rest) => collect_binding_names(&rest.arg, names),
+        Pat::Assign(assign) => collect_binding_names(&assign.left, names),
+        _ => {}
+    }
+}
+
+/// Rewrites a compiled module's imports and exports to use the bundle's namespace objects and wraps it
+/// in an async function so top level await and declarations keep working
+fn bundle_javascript(id: usize, specifier: &ModuleSpecifier, code: &str, is_main: bool, resolve: &dyn Fn(&str, DependencyKind) -> Option<(ModuleSpecifier, Option<usize>)>) -> Result<(String, ModuleExports), AnyError> {
+    let parsed = module_graph::parse_module(specifier, code)?;
+    let start = parsed.text_info_lazy().range().start;
+    let Program::Module(module) = parsed.program_ref() else {
+        return Err(generic_error(format!("{}: Expected a module", specifier)));
+    };
+
+    let byte_range = |from: SourcePos, to: SourcePos| SourceRange::new(from, to).as_byte_range(start);
+    let static_id = |src: &Str| -> Result<usize, AnyError> {
+        match resolve(&src.value, DependencyKind::Static) {
+            Some((_, Some(dependency_id))) => Ok(dependency_id),
+            _ => Err(generic_error(format!("{}: Unable to bundle import of \"{}\"", specifier, src.value)))
+        }
+    };
+
+    let mut edits: Vec<(Range<usize>, String)> = vec![];
+    let mut prologue: Vec<String> = vec![];
+    let mut getters: Vec<(String, String)> = vec![];
+    let mut exports = ModuleExports::default();
+
+    if let Some(shebang) = &module.shebang {
+        edits.push((0..shebang.len() + 2, String::new()));
+    }
+
+    for item in &module.body {
+        let ModuleItem::ModuleDecl(decl) = item else {
+            continue;
+        };
+        match decl {
+            ModuleDecl::Import(import) => {
+                edits.push((import.range().as_byte_range(start), String::new()));
+                if import.type_only {
+                    continue;
+                }
+                let dependency_id = static_id(&import.src)?;
+                let mut bindings = vec![];
+                for specifier in &import.specifiers {
+                    match specifier {
+                        ImportSpecifier::Default(default) => bindings.push(format!("default: {}", default.local.sym)),
+                        ImportSpecifier::Named(named) if !named.is_type_only => {
+                            let imported = named.imported.as_ref().map(export_name).unwrap_or_else(|| named.local.sym.to_string());
+                            bindings.push(format!("{}: {}", serde_json::to_string(&imported)?, named.local.sym));
+                        },
+                        ImportSpecifier::Namespace(namespace) => prologue.push(format!("const {} = __sjs_{dependency_id};", namespace.local.sym)),
+                        _ => {}
+                    }
+                }
+                if !bindings.is_empty() {
+                    prologue.push(format!("const {{ {} }} = __sjs_{dependency_id};", bindings.join(", ")));
+                }
+            },
+            ModuleDecl::ExportDecl(export) => {
+                edits.push((byte_range(export.range().start, export.decl.range().start), String::new()));
+                let mut names = vec![];
+                match &export.decl {
+                    Decl::Var(var) => for declarator in &var.decls {
+                        collect_binding_names(&declarator.name, &mut names);
+                    },
+                    Decl::Fn(function) => names.push(function.ident.sym.to_string()),
+                    Decl::Class(class) => names.push(class.ident.sym.to_string()),
+                    _ => {}
+                }
+                for name in names {
+                    getters.push((name.clone(), name));
+                }
+            },
+            ModuleDecl::ExportNamed(named) => {
+                edits.push((named.range().as_byte_range(start), String::new()));
+                if named.type_only {
+                    continue;
+                }
+                let dependency_id = named.src.as_deref().map(|src| static_id(src)).transpose()?;
+                for specifier in &named.specifiers {
+                    match (specifier, dependency_id) {
+                        (ExportSpecifier::Named(export), _) if export.is_type_only => {},
+                        (ExportSpecifier::Named(export), Some(dependency_id)) => {
+                            let exported = export.exported.as_ref().unwrap_or(&export.orig);
+                            getters.push((export_name(exported), format!("__sjs_{dependency_id}[{}]", serde_json::to_string(&export_name(&export.orig))?)));
+                        },
+                        (ExportSpecifier::Named(export), None) => {
+                            let exported = export.exported.as_ref().unwrap_or(&export.orig);
+                            getters.push((export_name(exported), export_name(&export.orig)));
+                        },
+                        (ExportSpecifier::Namespace(namespace), Some(dependency_id)) => getters.push((export_name(&namespace.name), format!("__sjs_{dependency_id}"))),
+                        _ => return Err(generic_error(format!("{}: Unsupported export syntax", specifier_name(specifier))))
+                    }
+                }
+            },
+            ModuleDecl::ExportDefaultDecl(export) => {
+                let ident = match &export.decl {
+                    DefaultDecl::Fn(function) => function.ident.as_ref(),
+                    DefaultDecl::Class(class) => class.ident.as_ref(),
+                    _ => continue
+                };
+                let prefix = byte_range(export.range().start, export.decl.range().start);
+                match ident {
+                    Some(ident) => {
+                        edits.push((prefix, String::new()));
+                        getters.push((String::from("default"), ident.sym.to_string()));
+                    },
+                    None => {
+                        let end = export.range().as_byte_range(start).end;
+                        edits.push((prefix, String::from("const __sjs_default = ")));
+                        edits.push((end..end, String::from(";")));
+                        getters.push((String::from("default"), String::from("__sjs_default")));
+                    }
+                }
+            },
+            ModuleDecl::ExportDefaultExpr(export) => {
+                edits.push((byte_range(export.range().start, export.expr.range().start), String::from("const __sjs_default = ")));
+                getters.push((String::from("default"), String::from("__sjs_default")));
+            },
+            ModuleDecl::ExportAll(export) => {
+                edits.push((export.range().as_byte_range(start), String::new()));
+                if !export.type_only {
+                    exports.star.push(static_id(&export.src)?);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    let mut rewriter = BodyRewriter {
+        id,
+        specifier,
+        start,
+        resolve,
+        edits: &mut edits,
+        error: None
+    };
+    module.visit_with(&mut rewriter);
+    if let Some(err) = rewriter.error {
+        return Err(err);
+    }
+
+    edits.sort_by_key(|(range, _)| (range.start, range.end));
+    let mut body = code.to_string();
+    for (range, replacement) in edits.into_iter().rev() {
+        body.replace_range(range, &replacement);
+    }
+
+    let mut source = format!(
+        "const __sjs_meta_{id} = {{ url: {url}, main: {is_main}, resolve: (specifier) => __sjs_resolve(specifier, {url}) }};\nawait (async () => {{\n",
+        url = serde_json::to_string(specifier.as_str())?
+    );
+    for line in prologue {
+        source.push_str(&line);
+        source.push('\n');
+    }
+    if !getters.is_empty() {
+        source.push_str(&format!("Object.defineProperties(__sjs_{id}, {{ {} }});\n", getters.iter().map(|(name, value)| {
+            format!("{}: {{ get: () => {}, enumerable: true }}", serde_json::to_string(name).unwrap(), value)
+        }).collect::<Vec<_>>().join(", ")));
+    }
+    for star in &exports.star {
+        source.push_str(&format!("__sjs_reexport(__sjs_{id}, __sjs_{star});\n"));
+    }
+    source.push_str(&body);
+    source.push_str("\n})();\n");
+
+    exports.names = getters.into_iter().map(|(name, _)| name).collect();
+    Ok((source, exports))
+}
+
+fn specifier_name(specifier: &ExportSpecifier) -> String {
+    match specifier {
+        ExportSpecifier::Named(named) => export_name(&named.orig),
+        ExportSpecifier::Namespace(namespace) => export_name(&namespace.name),
+        ExportSpecifier::Default(default) => default.exported.sym.to_string()
+    }
+}
+
+/// Points `import.meta` at a per-module object and dynamic imports at the namespace when the target is
+/// already part of the bundle, or at its absolute URL when it is remote. Local files that are only imported
+/// dynamically would leave the bundle pointing at this machine's file system, so those are an error.
+struct BodyRewriter<'a> {
+    id: usize,
+    specifier: &'a ModuleSpecifier,
+    start: StartSourcePos,
+    resolve: &'a dyn Fn(&str, DependencyKind) -> Option<(ModuleSpecifier, Option<usize>)>,
+    edits: &'a mut Vec<(Range<usize>, String)>,
+    error: Option<AnyError>
+}
+
+impl Visit for BodyRewriter<'_> {
+    fn visit_meta_prop_expr(&mut self, expr: &MetaPropExpr) {
+        if expr.kind == MetaPropKind::ImportMeta {
+            self.edits.push((expr.range().as_byte_range(self.start), format!("__sjs_meta_{}", self.id)));
+        }
+    }
+
+    fn visit_call_expr(&mut self, call: &CallExpr) {
+        if let Some((specifier, _)) = module_graph::get_dynamic_import(call) {
+            if let Some((resolved, _)) = (self.resolve)(&specifier.value, DependencyKind::Dynamic) {
+                if let Some((_, Some(dependency_id))) = (self.resolve)(&specifier.value, DependencyKind::Static) {
+                    self.edits.push((call.range().as_byte_range(self.start), format!("Promise.resolve(__sjs_{dependency_id})")));
+                    return;
+                }
+                if resolved.scheme() == "file" {
+                    self.error.get_or_insert_with(|| generic_error(format!("{}: Unable to bundle dynamic import of \"{}\", import it statically as well", self.specifier, specifier.value)));
+                    return;
+                }
+                self.edits.push((specifier.range().as_byte_range(self.start), serde_json::to_string(resolved.as_str()).unwrap()));
+            }
+        }
+        call.visit_children_with(self);
+    }
+}
@@ -0,0 +1,59 @@
+use deno_runtime::deno_core;
+use deno_core::error::AnyError;
+
+use crate::RuntimeOptions;
+use crate::util::module_graph::ModuleGraph;
+use crate::util::url::resolve_maybe_url;
+
+/// Downloads every remote module and npm package reachable from `entries` into the dependency cache without running anything
+///
+/// `#include`d files are expanded by mtsc as each module is loaded, so their imports are followed too,
+/// and specifiers are resolved through the import map the same way they would be at runtime.
+/// Returns `false` if any module failed to load.
+pub async fn cache(entries: Vec<String>, options: RuntimeOptions) -> Result<bool, AnyError> {
+    crate::init_v8();
+
+    let roots = entries.iter().map(resolve_maybe_url).collect::<Result<Vec<_>, _>>()?;
+    let Some(main_module) = roots.first() else {
+        return Ok(true);
+    };
+
+    let options = RuntimeOptions {
+        allow_remote: true,
+        ..options
+    };
+    let loader = crate::create_tool_loader(main_module, &options).await?;
+    let graph = ModuleGraph::build(&loader, roots.clone(), true).await;
+
+    let mut downloads: Vec<String> = loader.file_fetcher.downloads().iter().map(|specifier| specifier.to_string()).collect();
+    downloads.extend(loader.npm_resolver.iter().flat_map(|npm_resolver| npm_resolver.downloads()));
+    for specifier in &downloads {
+        eprintln!("\x1b[32mDownload\x1b[0m {}", specifier);
+    }
+
+    let mut cached = 0;
+    let mut failed = 0;
+    for graph_module in &graph.modules {
+        match &graph_module.module {
+            Ok(module) if matches!(module.file.specifier.scheme(), "http" | "https") => cached += 1,
+            Ok(_) => {},
+            Err(err) => {
+                eprintln!("\x1b[91;1merror\x1b[0m: {}", err);
+                failed += 1;
+            }
+        }
+        for dependency in &graph_module.dependencies {
+            if let Err(err) = &dependency.resolved {
+                eprintln!("\x1b[91;1merror\x1b[0m: {}: {}", dependency.specifier, err);
+                failed += 1;
+            }
+        }
+    }
+
+    eprintln!("{} | {} remote modules cached | {} downloaded | {} failed",
+        if failed == 0 { "\x1b[32mok\x1b[0m" } else { "\x1b[91mFAILED\x1b[0m" },
+        cached, downloads.len(), failed
+    );
+
+    Ok(failed == 0)
+}
@@ -0,0 +1,244 @@
+use deno_runtime::deno_core;
+use deno_core::{ModuleSpecifier,ModuleType};
+use deno_core::error::{generic_error,AnyError};
+use deno_core::serde_json;
+use deno_runtime::deno_permissions::PermissionsOptions;
+use deno_cache_dir::GlobalHttpCache;
+
+use serde::{Deserialize,Serialize};
+
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+
+use std::collections::{HashMap,HashSet};
+use std::fs;
+use std::io::{Read,Seek,SeekFrom};
+use std::path::{Path,PathBuf};
+use std::sync::Arc;
+
+use crate::{RuntimeOptions,SharedState};
+use crate::util::{File,FileFetcher,HttpClient,SJSCacheEnv,CacheSetting};
+use crate::util::module_graph::{DependencyKind,ModuleGraph};
+use crate::util::url::resolve_maybe_url;
+
+/// Marks the end of a binary with an embedded program, preceded by the payload's length
+const MAGIC_TRAILER: &[u8; 16] = b"sjs-standalone\0\0";
+
+#[derive(Serialize, Deserialize)]
+struct EmbeddedModule {
+    /// The final specifier after any redirects, relative imports are resolved against it
+    specifier: String,
+    headers: Option<HashMap<String, String>>,
+    /// Base64 encoded, JavaScript modules are stored after being compiled
+    source: String,
+}
+
+/// The program appended to a standalone binary
+#[derive(Serialize, Deserialize)]
+pub struct Standalone {
+    main_module: String,
+    modules: Vec<EmbeddedModule>,
+    /// Requested specifiers and the embedded specifiers they redirected to
+    #[serde(default)]
+    redirects: Vec<(String, String)>,
+    import_map: Option<(String, String)>,
+    macros: Vec<String>,
+    include_paths: Vec<String>,
+    permissions: PermissionsOptions,
+    #[serde(default)]
+    node_globals: bool,
+    #[serde(default)]
+    unstable_features: Vec<String>,
+    #[serde(default)]
+    seed: Option<u64>,
+}
+
+/// Appends `entry` and every module it can load to a copy of the running executable, programs that use npm
+/// packages are rejected
+pub async fn compile(entry: String, output: Option<String>, options: RuntimeOptions) -> Result<PathBuf, AnyError> {
+    crate::init_v8();
+
+    let main_module = resolve_maybe_url(&entry)?;
+    let loader = crate::create_tool_loader(&main_module, &options).await?;
+    let graph = ModuleGraph::build(&loader, vec![main_module.clone()], true).await;
+
+    // Dynamic imports that fail to load may never run, so only static imports have to succeed
+    let is_static = |specifier: &ModuleSpecifier| specifier == &main_module || graph.modules.iter().any(|graph_module| {
+        graph_module.dependencies.iter().any(|dependency| dependency.kind == DependencyKind::Static && dependency.resolved.as_ref().is_ok_and(|resolved| resolved == specifier))
+    });
+
+    let mut modules = vec![];
+    let mut redirects = vec![];
+    let mut embedded = HashSet::new();
+    for graph_module in &graph.modules {
+        let module = match &graph_module.module {
+            Ok(module) => module,
+            Err(err) if is_static(&graph_module.specifier) => return Err(generic_error(err.clone())),
+            Err(_) => continue
+        };
+        // Packages are resolved through `node_modules` style lookups in the npm cache, which the binary does not have
+        let is_npm = graph_module.specifier.scheme() == "npm" || loader.npm_resolver.as_ref().is_some_and(|npm_resolver| npm_resolver.module_format(&module.file.specifier).is_some());
+        if is_npm {
+            return Err(generic_error(format!("{}: npm packages cannot be compiled into standalone binaries", graph_module.specifier)));
+        }
+        if module.file.specifier != graph_module.specifier {
+            redirects.push((graph_module.specifier.to_string(), module.file.specifier.to_string()));
+        }
+        if !embedded.insert(module.file.specifier.clone()) {
+            continue;
+        }
+        let source = match (&module.module_type, &module.code) {
+            (ModuleType::JavaScript, Some(code)) => BASE64_STANDARD.encode(code),
+            _ => BASE64_STANDARD.encode(&module.file.source)
+        };
+        modules.push(EmbeddedModule {
+            specifier: module.file.specifier.to_string(),
+            headers: module.file.maybe_headers.clone(),
+            source
+        });
+    }
+
+    let standalone = Standalone {
+        main_module: main_module.to_string(),
+        modules,
+        redirects,
+        import_map: loader.import_map.as_ref().map(|import_map| (import_map.base_url().to_string(), import_map.to_json())),
+        macros: options.macros,
+        include_paths: options.include_paths,
+        permissions: options.permissions,
+        node_globals: options.node_globals,
+        unstable_features: options.unstable_features,
+        seed: options.seed,
+    };
+
+    let output = match output {
+        Some(output) => PathBuf::from(output),
+        None => {
+            let stem = Path::new(main_module.path()).file_stem().and_then(|x| x.to_str()).filter(|x| !x.is_empty()).unwrap_or("main");
+            PathBuf::from(stem)
+        }
+    };
+    let output = if cfg!(windows) && output.extension().is_none() { output.with_extension("exe") } else { output };
+
+    let mut binary = fs::read(std::env::current_exe()?)?;
+    let payload = serde_json::to_vec(&standalone)?;
+    binary.extend_from_slice(&payload);
+    binary.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    binary.extend_from_slice(MAGIC_TRAILER);
+
+    fs::write(&output, binary).map_err(|x| generic_error(format!("{}: {}", output.display(), x)))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&output, fs::Permissions::from_mode(0o755))?;
+    }
+
+    Ok(output)
+}
+
+fn find_payload(binary: &[u8]) -> Option<usize> {
+    let trailer = binary.len().checked_sub(MAGIC_TRAILER.len() + 8)?;
+    if &binary[trailer + 8..] != MAGIC_TRAILER {
+        return None;
+    }
+    Some(u64::from_le_bytes(binary[trailer..trailer + 8].try_into().unwrap()) as usize)
+}
+
+/// Reads the program embedded in the running executable, if there is one
+pub fn load_standalone() -> Result<Option<Standalone>, AnyError> {
+    let mut file = fs::File::open(std::env::current_exe()?)?;
+    let len = file.seek(SeekFrom::End(0))?;
+    if len < (MAGIC_TRAILER.len() + 8) as u64 {
+        return Ok(None);
+    }
+
+    let mut trailer = [0u8; 24];
+    file.seek(SeekFrom::End(-24))?;
+    file.read_exact(&mut trailer)?;
+    let Some(payload_len) = find_payload(&trailer) else {
+        return Ok(None);
+    };
+
+    if payload_len as u64 > len - trailer.len() as u64 {
+        return Err(generic_error("The embedded program is corrupt"));
+    }
+
+    let mut payload = vec![0u8; payload_len];
+    file.seek(SeekFrom::End(-24 - payload_len as i64))?;
+    file.read_exact(&mut payload)?;
+
+    Ok(Some(serde_json::from_slice(&payload)?))
+}
+
+/// Runs an embedded program entirely from memory, remote modules and the dependency cache are never used
+pub async fn run_standalone(standalone: Standalone, args: Vec<String>) -> Result<(), AnyError> {
+    crate::init_v8();
+
+    let file_fetcher = Arc::new(FileFetcher::new(
+        Arc::new(GlobalHttpCache::<SJSCacheEnv>::new(crate::get_temp_directory().join("libs"), SJSCacheEnv)),
+        CacheSetting::Only,
+        false,
+        Arc::new(HttpClient::new(Default::default(),None)),
+        Default::default(),
+    ));
+
+    // Only embedded modules were compiled, anything else the program loads, such as a local file that could not
+    // be embedded, is compiled as usual
+    let mut precompiled = HashSet::new();
+    for module in standalone.modules {
+        let specifier = ModuleSpecifier::parse(&module.specifier)?;
+        precompiled.insert(specifier.clone());
+        file_fetcher.insert_cached(File {
+            specifier,
+            maybe_headers: module.headers,
+            source: BASE64_STANDARD.decode(module.source)?.into(),
+        });
+    }
+    for (specifier, redirect) in standalone.redirects {
+        if let Some(file) = file_fetcher.get_source(&ModuleSpecifier::parse(&redirect)?) {
+            file_fetcher.insert_redirect(ModuleSpecifier::parse(&specifier)?, file);
+        }
+    }
+
+    let import_map = match standalone.import_map {
+        Some((base_url, json)) => Some(Arc::new(import_map::parse_from_json_with_options(
+            &ModuleSpecifier::parse(&base_url)?,
+            &json,
+            import_map::ImportMapOptions {
+                expand_imports: true,
+                ..Default::default()
+            }
+        )?.import_map)),
+        None => None
+    };
+
+    let main_module = ModuleSpecifier::parse(&standalone.main_module)?;
+    let permissions = crate::create_permissions(&RuntimeOptions {
+        permissions: standalone.permissions,
+        ..Default::default()
+    })?;
+
+    let shared = Arc::new(SharedState {
+        args0: std::env::args().next().unwrap_or_default(),
+        args,
+        blob_store: file_fetcher.blob_store().clone(),
+        file_fetcher,
+        macros: standalone.macros,
+        include_paths: standalone.include_paths,
+        import_map,
+        precompiled: Arc::new(precompiled),
+        unstable_features: crate::get_unstable_features(&standalone.unstable_features),
+        seed: standalone.seed,
+
+        ..Default::default()
+    });
+
+    let mut worker = crate::create_main_worker(shared, &main_module, permissions, false);
+    let node_globals = standalone.node_globals;
+    let result = async {
+        crate::preload_node_globals(&mut worker, node_globals).await?;
+        worker.execute_main_module(&main_module).await?;
+        worker.run_event_loop(false).await
+    }.await;
+    result.map_err(|err| crate::unstable_api_error(&mut worker.js_runtime).unwrap_or(err))
+}
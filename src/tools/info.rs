@@ -0,0 +1,198 @@
+use deno_runtime::deno_core;
+use deno_core::{resolve_import,ModuleSpecifier};
+use deno_core::error::AnyError;
+use deno_core::serde_json;
+
+use serde::Serialize;
+
+use std::collections::HashSet;
+use std::fmt::Write as _;
+
+use crate::RuntimeOptions;
+use crate::util::module_graph::{DependencyKind,ModuleDependency,ModuleGraph};
+use crate::util::url::resolve_maybe_url;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InfoModule {
+    /// The specifier as resolved, before any redirects
+    specifier: String,
+    final_specifier: Option<String>,
+    media_type: Option<String>,
+    size: Option<usize>,
+    /// The file on disk, for remote modules this is their location in the dependency cache
+    local: Option<String>,
+    error: Option<String>,
+    dependencies: Vec<InfoDependency>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InfoDependency {
+    specifier: String,
+    dynamic: bool,
+    resolved: Option<String>,
+    /// Set when the import map changed where the specifier resolves to
+    mapped: bool,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Info {
+    roots: Vec<String>,
+    import_map: Option<String>,
+    modules: Vec<InfoModule>,
+}
+
+/// Describes the module graph of `entry` as a tree, or as JSON when `json` is set
+pub async fn info(entry: String, json: bool, options: RuntimeOptions) -> Result<String, AnyError> {
+    crate::init_v8();
+
+    let main_module = resolve_maybe_url(&entry)?;
+    let options = RuntimeOptions {
+        allow_remote: true,
+        ..options
+    };
+    let loader = crate::create_tool_loader(&main_module, &options).await?;
+    let graph = ModuleGraph::build(&loader, vec![main_module.clone()], true).await;
+    let http_cache = crate::create_http_cache(&options);
+
+    let info = Info {
+        roots: graph.roots.iter().map(|root| root.to_string()).collect(),
+        import_map: loader.import_map.as_ref().map(|import_map| import_map.base_url().to_string()),
+        modules: graph.modules.iter().map(|graph_module| {
+            let dependencies = graph_module.dependencies.iter().map(|dependency| InfoDependency {
+                specifier: dependency.specifier.clone(),
+                dynamic: dependency.kind == DependencyKind::Dynamic,
+                resolved: dependency.resolved.as_ref().ok().map(|resolved| resolved.to_string()),
+                mapped: loader.import_map.is_some() && is_mapped(dependency, &graph_module.specifier),
+                error: dependency.resolved.as_ref().err().cloned(),
+            }).collect();
+
+            match &graph_module.module {
+                Ok(module) => {
+                    let specifier = &module.file.specifier;
+                    let local = match specifier.scheme() {
+                        "file" => specifier.to_file_path().ok(),
+                        "http" | "https" => http_cache.get_global_cache_filepath(specifier).ok(),
+                        _ => None
+                    };
+                    InfoModule {
+                        specifier: graph_module.specifier.to_string(),
+                        final_specifier: Some(specifier.to_string()),
+                        media_type: Some(module.file.resolve_media_type_and_charset().0.to_string()),
+                        size: Some(module.file.source.len()),
+                        local: local.map(|path| path.display().to_string()),
+                        error: None,
+                        dependencies
+                    }
+                },
+                Err(err) => InfoModule {
+                    specifier: graph_module.specifier.to_string(),
+                    final_specifier: None,
+                    media_type: None,
+                    size: None,
+                    local: None,
+                    error: Some(err.clone()),
+                    dependencies
+                }
+            }
+        }).collect()
+    };
+
+    if json {
+        return Ok(serde_json::to_string_pretty(&info)? + "\n");
+    }
+
+    let mut output = String::new();
+    if let Some(import_map) = &info.import_map {
+        writeln!(output, "\x1b[1mimport map:\x1b[0m {}", import_map)?;
+    }
+    if let Some(module) = graph.index_of(&main_module).map(|index| &info.modules[index]) {
+        if let Some(local) = &module.local {
+            writeln!(output, "\x1b[1mlocal:\x1b[0m {}", local)?;
+        }
+        if let Some(media_type) = &module.media_type {
+            writeln!(output, "\x1b[1mtype:\x1b[0m {}", media_type)?;
+        }
+        writeln!(output, "\x1b[1mdependencies:\x1b[0m {} unique", info.modules.len() - 1)?;
+        writeln!(output, "\x1b[1msize:\x1b[0m {}", human_size(info.modules.iter().filter_map(|module| module.size).sum()))?;
+        writeln!(output)?;
+    }
+
+    let mut seen = HashSet::new();
+    write_tree(&mut output, &info, &graph, &main_module, None, "", &mut seen)?;
+    Ok(output)
+}
+
+/// Whether the import map sent a specifier somewhere other than where plain URL resolution would
+fn is_mapped(dependency: &ModuleDependency, referrer: &ModuleSpecifier) -> bool {
+    match &dependency.resolved {
+        Ok(resolved) => resolve_import(&dependency.specifier, referrer.as_str()).map_or(true, |unmapped| &unmapped != resolved),
+        Err(_) => false
+    }
+}
+
+fn write_tree(output: &mut String, info: &Info, graph: &ModuleGraph, specifier: &ModuleSpecifier, dependency: Option<&InfoDependency>, prefix: &str, seen: &mut HashSet<ModuleSpecifier>) -> Result<(), std::fmt::Error> {
+    let Some(index) = graph.index_of(specifier) else {
+        return Ok(());
+    };
+    let module = &info.modules[index];
+    let expand = seen.insert(specifier.clone());
+
+    let mut label = String::new();
+    if let Some(dependency) = dependency.filter(|dependency| dependency.mapped) {
+        write!(label, "\"{}\" \x1b[90m→\x1b[0m ", dependency.specifier)?;
+    }
+    match &module.final_specifier {
+        Some(final_specifier) if final_specifier != &module.specifier => write!(label, "{} \x1b[90m→\x1b[0m {}", module.specifier, final_specifier)?,
+        _ => write!(label, "{}", module.specifier)?
+    }
+    if let (Some(media_type), Some(size)) = (&module.media_type, module.size) {
+        write!(label, " \x1b[90m({}, {})\x1b[0m", media_type, human_size(size))?;
+    }
+    if dependency.is_some_and(|dependency| dependency.dynamic) {
+        write!(label, " \x1b[90m(dynamic)\x1b[0m")?;
+    }
+    if let Some(local) = module.local.as_ref().filter(|_| !module.specifier.starts_with("file:")) {
+        write!(label, " \x1b[90m{}\x1b[0m", local)?;
+    }
+    if let Some(err) = &module.error {
+        write!(label, " \x1b[91;1merror\x1b[0m: {}", err)?;
+    }
+    if !expand && !module.dependencies.is_empty() {
+        write!(label, " \x1b[90m*\x1b[0m")?;
+    }
+    writeln!(output, "{}", label)?;
+
+    if !expand {
+        return Ok(());
+    }
+
+    for (i, (child, info_child)) in graph.modules[index].dependencies.iter().zip(&module.dependencies).enumerate() {
+        let last = i == module.dependencies.len() - 1;
+        write!(output, "{}{}", prefix, if last { "└── " } else { "├── " })?;
+        let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+        match &child.resolved {
+            Ok(resolved) => write_tree(output, info, graph, resolved, Some(info_child), &child_prefix, seen)?,
+            Err(err) => writeln!(output, "{} \x1b[91;1merror\x1b[0m: {}", child.specifier, err)?
+        }
+    }
+    Ok(())
+}
+
+fn human_size(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}B", bytes)
+    } else {
+        format!("{:.2}{}", size, UNITS[unit])
+    }
+}
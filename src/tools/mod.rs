@@ -0,0 +1,23 @@
+mod test;
+pub use test::test;
+
+mod bundle;
+pub use bundle::bundle;
+
+mod compile;
+pub use compile::{compile,load_standalone,run_standalone,Standalone};
+
+mod repl;
+pub use repl::repl;
+
+mod cache;
+pub use cache::cache;
+
+mod info;
+pub use info::info;
+
+mod watch;
+pub use watch::{watch,WatchOptions};
+
+mod vendor;
+pub use vendor::vendor;
@@ -0,0 +1,249 @@
+use deno_runtime::deno_core;
+use deno_core::{ModuleSpecifier,PollEventLoopOptions};
+use deno_core::error::AnyError;
+use deno_core::futures::FutureExt;
+use deno_core::serde_json::{json,Value};
+use deno_core::LocalInspectorSession;
+use deno_runtime::worker::MainWorker;
+
+use rustyline::{Editor,Helper};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::{ValidationContext,ValidationResult,Validator};
+
+use crate::RuntimeOptions;
+
+/// Asks for more lines while brackets, strings or comments are left open
+struct ReplHelper;
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        Ok(if is_complete(ctx.input()) { ValidationResult::Valid(None) } else { ValidationResult::Incomplete })
+    }
+}
+
+impl Helper for ReplHelper {}
+
+fn is_complete(input: &str) -> bool {
+    let mut stack: Vec<char> = vec![];
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match stack.last() {
+            Some(&quote @ ('\'' | '"')) => match c {
+                '\\' => { chars.next(); },
+                '\n' => { stack.pop(); },
+                _ if c == quote => { stack.pop(); },
+                _ => {}
+            },
+            Some('`') => match c {
+                '\\' => { chars.next(); },
+                '`' => { stack.pop(); },
+                '$' if chars.peek() == Some(&'{') => { chars.next(); stack.push('{'); },
+                _ => {}
+            },
+            Some('*') => if c == '*' && chars.peek() == Some(&'/') {
+                chars.next();
+                stack.pop();
+            },
+            _ => match c {
+                '\'' | '"' | '`' | '(' | '[' | '{' => stack.push(c),
+                '/' if chars.peek() == Some(&'/') => while chars.next_if(|&c| c != '\n').is_some() {},
+                '/' if chars.peek() == Some(&'*') => { chars.next(); stack.push('*'); },
+                ')' | ']' | '}' => {
+                    // Unbalanced closing brackets are left for the parser to report
+                    if stack.pop().is_none() {
+                        return true;
+                    }
+                },
+                _ => {}
+            }
+        }
+    }
+    stack.iter().all(|&c| c == '\'' || c == '"')
+}
+
+/// Runs an inspector method while driving the event loop so awaited promises can settle
+async fn post_message(worker: &mut MainWorker, session: &mut LocalInspectorSession, method: &str, params: Option<Value>) -> Result<Value, AnyError> {
+    worker.js_runtime.with_event_loop_future(
+        session.post_message(method, params).boxed_local(),
+        PollEventLoopOptions {
+            pump_v8_message_loop: false,
+            ..Default::default()
+        }
+    ).await
+}
+
+fn report_error(err: &AnyError) {
+    eprintln!("\x1b[91;1merror\x1b[0m: Uncaught {}", err);
+}
+
+/// Converts a `Runtime.RemoteObject` into a `Runtime.CallArgument`
+fn call_argument(object: &Value) -> Value {
+    if let Some(object_id) = object.get("objectId") {
+        json!({ "objectId": object_id })
+    } else if let Some(value) = object.get("unserializableValue") {
+        json!({ "unserializableValue": value })
+    } else if let Some(value) = object.get("value") {
+        json!({ "value": value })
+    } else {
+        json!({})
+    }
+}
+
+/// Starts an interactive session, each input is preprocessed and transpiled with mtsc before being evaluated
+pub async fn repl(options: RuntimeOptions) -> Result<(), AnyError> {
+    crate::init_v8();
+
+    let main_module = ModuleSpecifier::parse("sjs://repl").unwrap();
+    let file_fetcher = crate::create_file_fetcher(&options)?;
+    let permissions = crate::create_permissions(&options)?;
+    let shared = crate::create_shared_state(&main_module, String::new(), vec![], file_fetcher, &options, None).await?;
+    let mut worker = crate::create_main_worker(shared, &main_module, permissions, false);
+    crate::preload_node_globals(&mut worker, options.node_globals).await?;
+
+    worker.js_runtime.maybe_init_inspector();
+    let mut session = worker.create_inspector_session();
+    post_message(&mut worker, &mut session, "Runtime.enable", None).await?;
+
+    let global_this = post_message(&mut worker, &mut session, "Runtime.evaluate", Some(json!({ "expression": "globalThis" }))).await?;
+    let global_this_id = global_this["result"]["objectId"].clone();
+
+    let history_path = options.storage_dir.clone().or_else(crate::get_storage_directory).map(|path| path.join("repl_history.txt"));
+    let mut editor = Editor::<ReplHelper, DefaultHistory>::new()?;
+    editor.set_helper(Some(ReplHelper));
+    if let Some(path) = &history_path {
+        let _ = editor.load_history(path);
+    }
+
+    println!("SJS {}", crate::version());
+    println!("exit using ctrl+d or .exit");
+
+    loop {
+        // Read on another thread so timers and other pending work keep running between inputs
+        let mut line = tokio::task::spawn_blocking(move || {
+            let line = editor.readline("> ");
+            (editor, line)
+        });
+        let joined = loop {
+            match worker.js_runtime.with_event_loop_future(
+                async { Ok::<_, AnyError>((&mut line).await) }.boxed_local(),
+                PollEventLoopOptions::default()
+            ).await {
+                Ok(joined) => break joined,
+                // An uncaught error in a timer or promise does not end the session, the input is still being read
+                Err(err) => report_error(&err)
+            }
+        };
+        let (returned_editor, line) = joined?;
+        editor = returned_editor;
+
+        let input = match line {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(err.into())
+        };
+        if input.trim().is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(input.as_str());
+        if input.trim() == ".exit" {
+            break;
+        }
+
+        let code = match mtsc::compile(&input, &mtsc::Options {
+            module: false,
+            preprocess: true,
+            transpile: true,
+            filename: Some(main_module.clone().into()),
+            macros: options.macros.clone(),
+            include_paths: options.include_paths.clone(),
+            ..Default::default()
+        }) {
+            Some(code) => code,
+            None => {
+                eprintln!("\x1b[91;1merror\x1b[0m: Failed to compile input");
+                continue;
+            }
+        };
+
+        let evaluated = match post_message(&mut worker, &mut session, "Runtime.evaluate", Some(json!({
+            "expression": code,
+            "objectGroup": "repl",
+            "awaitPromise": true,
+            "replMode": true,
+            "userGesture": true,
+            "allowUnsafeEvalBlockedByCSP": true,
+        }))).await {
+            Ok(evaluated) => evaluated,
+            Err(err) => {
+                report_error(&err);
+                continue;
+            }
+        };
+
+        // Let the runtime's console do the formatting
+        let (function, value) = match evaluated.get("exceptionDetails") {
+            Some(exception) => ("function (error) { console.error('Uncaught', error); }", &exception["exception"]),
+            None => ("function (value) { console.log(value); }", &evaluated["result"])
+        };
+        if let Err(err) = post_message(&mut worker, &mut session, "Runtime.callFunctionOn", Some(json!({
+            "functionDeclaration": function,
+            "objectId": global_this_id,
+            "arguments": [call_argument(value)],
+        }))).await {
+            report_error(&err);
+        }
+    }
+
+    if let Some(path) = &history_path {
+        let _ = std::fs::create_dir_all(path.parent().unwrap());
+        let _ = editor.save_history(path);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complete_input() {
+        assert!(is_complete("1 + 1"));
+        assert!(is_complete("function f() { return [1, 2]; }"));
+        assert!(is_complete("const s = \"{[(\";"));
+        assert!(is_complete("`${a + `${b}`}`"));
+        assert!(is_complete("f() // {"));
+        assert!(is_complete("/* ( */ 1"));
+    }
+
+    #[test]
+    fn incomplete_input() {
+        assert!(!is_complete("function f() {"));
+        assert!(!is_complete("[1,\n2"));
+        assert!(!is_complete("`line\n"));
+        assert!(!is_complete("`${a"));
+        assert!(!is_complete("/* comment"));
+    }
+
+    #[test]
+    fn leaves_errors_to_the_parser() {
+        // Unterminated strings and unbalanced brackets are syntax errors, not continuation lines
+        assert!(is_complete("\"unterminated"));
+        assert!(is_complete("1 + 1)"));
+    }
+}
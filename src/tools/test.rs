@@ -0,0 +1,248 @@
+use deno_runtime::deno_core;
+use deno_core::{v8,serde_v8,ModuleSpecifier,PollEventLoopOptions};
+use deno_runtime::worker::MainWorker;
+
+use serde::Deserialize;
+
+use std::path::{Path,PathBuf};
+use std::time::{Duration,Instant};
+
+use crate::{AnyError,RuntimeOptions};
+use crate::util::url::resolve_maybe_url;
+
+const TEST_EXTENSIONS: [&str; 6] = ["js", "mjs", "jsx", "ts", "mts", "tsx"];
+
+/// Replaces the runtime's no-op `system.test` with one that records each test
+const REGISTER_TESTS: &str = r#"
+((system) => {
+    const tests = [];
+    function test(nameOrOptions, optionsOrFn, maybeFn) {
+        let options;
+        if (typeof nameOrOptions === "function") {
+            options = { name: nameOrOptions.name, fn: nameOrOptions };
+        } else if (typeof nameOrOptions === "string") {
+            options = typeof optionsOrFn === "function" ? { name: nameOrOptions, fn: optionsOrFn } : { ...optionsOrFn, name: nameOrOptions, fn: maybeFn };
+        } else {
+            options = { ...nameOrOptions };
+        }
+        if (typeof options.fn !== "function") {
+            throw new TypeError("Missing test function");
+        }
+        if (!options.name) {
+            throw new TypeError("The test name can't be empty");
+        }
+        tests.push({ name: String(options.name), fn: options.fn, ignore: !!options.ignore, only: !!options.only });
+    }
+    Object.defineProperty(system, "test", { value: test, writable: true, enumerable: true, configurable: true });
+    globalThis[Symbol.for("sjs.tests")] = tests;
+})(globalThis.system);
+"#;
+
+/// Runs every registered test in order and resolves to their results
+const RUN_TESTS: &str = r#"
+(async () => {
+    const tests = globalThis[Symbol.for("sjs.tests")];
+    const only = tests.some(test => test.only);
+    const results = [];
+    for (const { name, fn, ignore, only: isOnly } of tests) {
+        if (ignore || (only && !isOnly)) {
+            results.push({ name, status: "ignored", duration: 0 });
+            continue;
+        }
+        const start = performance.now();
+        try {
+            await fn();
+            results.push({ name, status: "ok", duration: performance.now() - start });
+        } catch (error) {
+            results.push({ name, status: "failed", duration: performance.now() - start, error: error instanceof Error && error.stack ? error.stack : String(error) });
+        }
+    }
+    return results;
+})()
+"#;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum TestStatus {
+    Ok,
+    Failed,
+    Ignored
+}
+
+#[derive(Deserialize)]
+struct TestResult {
+    name: String,
+    status: TestStatus,
+    duration: f64,
+    error: Option<String>
+}
+
+#[derive(Default)]
+struct TestSummary {
+    passed: usize,
+    failed: usize,
+    ignored: usize,
+    failures: Vec<(ModuleSpecifier, String, String)>
+}
+
+/// Runs each test file found in `paths` in a fresh worker and reports the results,
+/// returns whether every test passed
+pub async fn test(paths: Vec<String>, options: RuntimeOptions) -> Result<bool, AnyError> {
+    crate::init_v8();
+
+    let specifiers = collect_test_modules(if paths.is_empty() { vec![String::from(".")] } else { paths })?;
+    let file_fetcher = crate::create_file_fetcher(&options)?;
+
+    let start = Instant::now();
+    let mut summary = TestSummary::default();
+
+    for specifier in specifiers {
+        let permissions = crate::create_permissions(&options)?;
+        let shared = crate::create_shared_state(&specifier, specifier.to_string(), vec![], file_fetcher.clone(), &options, None).await?;
+        let mut worker = crate::create_main_worker(shared, &specifier, permissions, false);
+
+        let results = run_test_module(&mut worker, &specifier, options.node_globals).await
+            .map_err(|err| crate::unstable_api_error(&mut worker.js_runtime).unwrap_or(err));
+        match results {
+            Ok(results) => {
+                println!("running {} test{} from {}", results.len(), if results.len() == 1 { "" } else { "s" }, specifier);
+                for result in results {
+                    let duration = format_duration(Duration::from_secs_f64(result.duration / 1000.0));
+                    match result.status {
+                        TestStatus::Ok => {
+                            summary.passed += 1;
+                            println!("{} ... \x1b[32mok\x1b[0m \x1b[90m({})\x1b[0m", result.name, duration);
+                        },
+                        TestStatus::Failed => {
+                            summary.failed += 1;
+                            println!("{} ... \x1b[91mFAILED\x1b[0m \x1b[90m({})\x1b[0m", result.name, duration);
+                            summary.failures.push((specifier.clone(), result.name, result.error.unwrap_or_default()));
+                        },
+                        TestStatus::Ignored => {
+                            summary.ignored += 1;
+                            println!("{} ... \x1b[93mignored\x1b[0m", result.name);
+                        }
+                    }
+                }
+            },
+            Err(err) => {
+                summary.failed += 1;
+                println!("{} \x1b[91mFAILED\x1b[0m", specifier);
+                summary.failures.push((specifier.clone(), String::from("<module>"), format!("{}", err)));
+            }
+        }
+    }
+
+    if !summary.failures.is_empty() {
+        println!("\n\x1b[97;41m ERRORS \x1b[0m\n");
+        for (specifier, name, error) in &summary.failures {
+            println!("{} => {}", name, specifier);
+            println!("\x1b[91;1merror\x1b[0m: {}\n", error);
+        }
+    }
+
+    println!("{} | {} passed | {} failed | {} ignored \x1b[90m({})\x1b[0m",
+        if summary.failed == 0 { "\x1b[32mok\x1b[0m" } else { "\x1b[91mFAILED\x1b[0m" },
+        summary.passed,
+        summary.failed,
+        summary.ignored,
+        format_duration(start.elapsed())
+    );
+
+    Ok(summary.failed == 0)
+}
+
+async fn run_test_module(worker: &mut MainWorker, specifier: &ModuleSpecifier, node_globals: bool) -> Result<Vec<TestResult>, AnyError> {
+    worker.execute_script("[sjs:test]", REGISTER_TESTS.to_string().into())?;
+    crate::preload_node_globals(worker, node_globals).await?;
+    worker.execute_main_module(specifier).await?;
+
+    let promise = worker.execute_script("[sjs:test]", RUN_TESTS.to_string().into())?;
+    let resolved = worker.js_runtime.resolve(promise);
+    let value = worker.js_runtime.with_event_loop_promise(resolved, PollEventLoopOptions::default()).await?;
+
+    let scope = &mut worker.js_runtime.handle_scope();
+    let value = v8::Local::new(scope, value);
+    Ok(serde_v8::from_v8(scope, value)?)
+}
+
+/// Expands directories into the test files they contain, explicitly listed files and URLs are kept as is
+fn collect_test_modules(paths: Vec<String>) -> Result<Vec<ModuleSpecifier>, AnyError> {
+    let mut specifiers = vec![];
+    for path in paths {
+        let specifier = resolve_maybe_url(&path)?;
+        match specifier.to_file_path() {
+            Ok(file_path) if file_path.is_dir() => {
+                let mut files = vec![];
+                collect_test_files(&file_path, &mut files).map_err(|x| AnyError::msg(format!("{}: {}", path, x)))?;
+                files.sort();
+                for file in files {
+                    let specifier = ModuleSpecifier::from_file_path(&file).map_err(|_| AnyError::msg(format!("{}: {}", file.display(), "Invalid file or URL")))?;
+                    if !specifiers.contains(&specifier) {
+                        specifiers.push(specifier);
+                    }
+                }
+            },
+            _ if !specifiers.contains(&specifier) => specifiers.push(specifier),
+            _ => {}
+        }
+    }
+    Ok(specifiers)
+}
+
+fn collect_test_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        if name.starts_with('.') || name == "node_modules" {
+            continue;
+        }
+        if path.is_dir() {
+            collect_test_files(&path, files)?;
+        } else if is_test_file(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Matches `test.ts`, `*_test.ts` and `*.test.ts` along with the other script extensions
+fn is_test_file(path: &Path) -> bool {
+    let (Some(stem), Some(ext)) = (path.file_stem().and_then(|x| x.to_str()), path.extension().and_then(|x| x.to_str())) else {
+        return false;
+    };
+    TEST_EXTENSIONS.contains(&ext) && (stem == "test" || stem.ends_with("_test") || stem.ends_with(".test"))
+}
+
+fn format_duration(duration: Duration) -> String {
+    if duration.as_secs() > 0 {
+        format!("{:.2}s", duration.as_secs_f64())
+    } else {
+        format!("{}ms", duration.as_millis())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_test_files() {
+        for name in ["test.ts", "foo_test.js", "foo.test.tsx", "dir/bar.test.mjs", "test.mts"] {
+            assert!(is_test_file(Path::new(name)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn skips_other_files() {
+        for name in ["foo.ts", "testing.ts", "footest.ts", "foo_test.json", "foo.test", "test"] {
+            assert!(!is_test_file(Path::new(name)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(Duration::from_millis(42)), "42ms");
+        assert_eq!(format_duration(Duration::from_millis(1500)), "1.50s");
+    }
+}
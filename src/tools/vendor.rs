@@ -0,0 +1,183 @@
+use deno_runtime::deno_core;
+use deno_core::ModuleSpecifier;
+use deno_core::error::{generic_error,AnyError};
+use deno_core::serde_json::{self,json,Map,Value};
+
+use deno_ast::MediaType;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path,PathBuf};
+
+use crate::RuntimeOptions;
+use crate::util::hash;
+use crate::util::jsonc::strip_jsonc;
+use crate::util::module_graph::{DependencyKind,ModuleGraph};
+use crate::util::url::resolve_maybe_url;
+
+/// Copies every remote module reachable from `entry` into `output` (By default `vendor/` next to the import map)
+/// and points the import map at the copies so the program no longer needs network access
+pub async fn vendor(entry: String, output: Option<String>, options: RuntimeOptions) -> Result<PathBuf, AnyError> {
+    crate::init_v8();
+
+    let main_module = resolve_maybe_url(&entry)?;
+    let options = RuntimeOptions {
+        allow_remote: true,
+        ..options
+    };
+    let loader = crate::create_tool_loader(&main_module, &options).await?;
+    let graph = ModuleGraph::build(&loader, vec![main_module.clone()], true).await;
+
+    // Local import maps are updated in place, otherwise one is created where it would be found by default
+    let import_map_path = match loader.import_map.as_ref().map(|import_map| import_map.base_url()) {
+        Some(base_url) if base_url.scheme() == "file" => base_url.to_file_path().map_err(|_| generic_error(format!("{}: Invalid file path", base_url)))?,
+        _ => match main_module.to_file_path() {
+            Ok(path) if main_module.scheme() == "file" => path.with_file_name("imports.json"),
+            _ => std::env::current_dir()?.join("imports.json")
+        }
+    };
+    let base_dir = import_map_path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let vendor_dir = match output {
+        Some(output) => std::env::current_dir()?.join(output),
+        None => base_dir.join("vendor")
+    };
+
+    let is_static = |specifier: &ModuleSpecifier| specifier == &main_module || graph.modules.iter().any(|graph_module| {
+        graph_module.dependencies.iter().any(|dependency| dependency.kind == DependencyKind::Static && dependency.resolved.as_ref().is_ok_and(|resolved| resolved == specifier))
+    });
+
+    // Hosts are mapped as a whole so absolute imports between vendored modules resolve too, anything
+    // that does not mirror its URL (redirects, queries and added extensions) is mapped individually
+    let mut imports: BTreeMap<String, String> = BTreeMap::new();
+    let mut count = 0;
+    for graph_module in &graph.modules {
+        let module = match &graph_module.module {
+            Ok(module) => module,
+            Err(err) if is_static(&graph_module.specifier) => return Err(generic_error(err.clone())),
+            Err(err) => {
+                eprintln!("\x1b[93mwarning\x1b[0m: Skipping dynamic import {}", err);
+                continue;
+            }
+        };
+        let specifier = &module.file.specifier;
+        if !matches!(specifier.scheme(), "http" | "https") {
+            continue;
+        }
+
+        let relative_path = vendor_path(specifier, module.file.resolve_media_type_and_charset().0);
+        let renamed = relative_path != vendor_path(specifier, MediaType::Unknown);
+        let path = vendor_dir.join(relative_path);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(&path, &module.file.source).map_err(|x| generic_error(format!("{}: {}", path.display(), x)))?;
+        count += 1;
+
+        let host = format!("{}://{}/", specifier.scheme(), specifier.authority());
+        imports.insert(host.clone(), local_url(&vendor_dir.join(vendor_host(specifier)), &base_dir, true));
+        for requested in [&graph_module.specifier, specifier] {
+            if requested.query().is_some() || requested != specifier || renamed {
+                imports.insert(requested.to_string(), local_url(&path, &base_dir, false));
+            }
+        }
+    }
+
+    let mut import_map: Value = match &loader.import_map {
+        // Comments and trailing commas are allowed in import maps, but are lost when the map is rewritten
+        Some(import_map) if import_map.base_url().scheme() == "file" => serde_json::from_str(&strip_jsonc(&fs::read_to_string(&import_map_path)?))
+            .map_err(|x| generic_error(format!("{}: {}", import_map_path.display(), x)))?,
+        Some(import_map) => serde_json::from_str(&import_map.to_json())?,
+        None => json!({})
+    };
+    let root = import_map.as_object_mut().ok_or_else(|| generic_error(format!("{}: Import map must be an object", import_map_path.display())))?;
+
+    // Import maps are only applied once, so existing entries that point at vendored URLs must point at the copies instead
+    let rewrite = |value: &mut Value| if let Some(target) = value.as_str() {
+        if let Some((prefix, local)) = imports.iter().filter(|(prefix, _)| target.starts_with(prefix.as_str())).max_by_key(|(prefix, _)| prefix.len()) {
+            *value = Value::String(if prefix.ends_with('/') { format!("{}{}", local, &target[prefix.len()..]) } else { local.clone() });
+        }
+    };
+    let mut scopes = root.get_mut("scopes").and_then(Value::as_object_mut).map(std::mem::take).unwrap_or_default();
+    for scope in scopes.values_mut().filter_map(Value::as_object_mut) {
+        scope.values_mut().for_each(rewrite);
+    }
+
+    let entries = root.entry("imports").or_insert_with(|| Value::Object(Map::new())).as_object_mut().ok_or_else(|| generic_error(format!("{}: \"imports\" must be an object", import_map_path.display())))?;
+    entries.values_mut().for_each(rewrite);
+    for (url, local) in &imports {
+        entries.insert(url.clone(), Value::String(local.clone()));
+    }
+    if !scopes.is_empty() {
+        root.insert(String::from("scopes"), Value::Object(scopes));
+    }
+
+    fs::write(&import_map_path, serde_json::to_string_pretty(&import_map)? + "\n").map_err(|x| generic_error(format!("{}: {}", import_map_path.display(), x)))?;
+    eprintln!("Vendored {} remote modules into {}", count, vendor_dir.display());
+    Ok(import_map_path)
+}
+
+/// Hosts with ports become `host_port` since `:` is not allowed in Windows paths
+fn vendor_host(specifier: &ModuleSpecifier) -> String {
+    match specifier.port() {
+        Some(port) => format!("{}_{}", specifier.host_str().unwrap_or_default(), port),
+        None => specifier.host_str().unwrap_or_default().to_string()
+    }
+}
+
+/// Mirrors a URL's host and path, queries are folded into the file name so different ones do not collide. The copy has
+/// no `Content-Type`, so a file name without an extension gets the one of the media type it was served as
+fn vendor_path(specifier: &ModuleSpecifier, media_type: MediaType) -> PathBuf {
+    let mut path = PathBuf::from(vendor_host(specifier));
+    let segments: Vec<&str> = specifier.path_segments().map(|segments| segments.collect()).unwrap_or_default();
+    for (i, segment) in segments.iter().enumerate() {
+        if i < segments.len() - 1 {
+            path.push(segment);
+            continue;
+        }
+
+        let is_index = segment.is_empty();
+        let name = Path::new(if is_index { "index" } else { segment });
+        let stem = name.file_stem().and_then(|x| x.to_str()).unwrap_or(segment);
+        let ext = name.extension().and_then(|x| x.to_str())
+            .or_else(|| media_type_extension(media_type))
+            .or_else(|| is_index.then_some("js"));
+        let stem = match specifier.query() {
+            Some(query) => format!("{}_{}", stem, hash(query)),
+            None => stem.to_string()
+        };
+        path.push(match ext {
+            Some(ext) => format!("{}.{}", stem, ext),
+            None => stem
+        });
+    }
+    path
+}
+
+fn media_type_extension(media_type: MediaType) -> Option<&'static str> {
+    match media_type {
+        MediaType::JavaScript => Some("js"),
+        MediaType::Mjs => Some("mjs"),
+        MediaType::Cjs => Some("cjs"),
+        MediaType::Jsx => Some("jsx"),
+        MediaType::TypeScript => Some("ts"),
+        MediaType::Mts => Some("mts"),
+        MediaType::Cts => Some("cts"),
+        MediaType::Dts => Some("d.ts"),
+        MediaType::Dmts => Some("d.mts"),
+        MediaType::Dcts => Some("d.cts"),
+        MediaType::Tsx => Some("tsx"),
+        MediaType::Json => Some("json"),
+        MediaType::Wasm => Some("wasm"),
+        _ => None
+    }
+}
+
+/// Import map addresses are relative to the import map when possible
+fn local_url(path: &Path, base_dir: &Path, directory: bool) -> String {
+    let mut url = match path.strip_prefix(base_dir) {
+        Ok(relative) => format!("./{}", relative.components().map(|component| component.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/")),
+        Err(_) => ModuleSpecifier::from_file_path(path).map(|url| url.to_string()).unwrap_or_else(|_| path.display().to_string())
+    };
+    if directory && !url.ends_with('/') {
+        url.push('/');
+    }
+    url
+}
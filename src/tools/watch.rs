@@ -0,0 +1,130 @@
+use deno_runtime::deno_core;
+use deno_core::error::AnyError;
+
+use notify::{EventKind,RecursiveMode,Watcher};
+use tokio::sync::mpsc;
+
+use std::collections::HashSet;
+use std::path::{Path,PathBuf};
+use std::time::Duration;
+
+use crate::{InspectorOptions,RuntimeOptions,ScriptSource};
+use crate::util::url::resolve_maybe_url;
+use crate::util::watch::WatchedFiles;
+
+pub struct WatchOptions {
+    /// Clear the terminal before each restart
+    pub clear_screen: bool,
+    /// How long to wait for changes to stop before restarting
+    pub debounce: Duration,
+    /// Other files that restart the program when they change, such as the config file
+    pub paths: Vec<PathBuf>,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            clear_screen: true,
+            debounce: Duration::from_millis(200),
+            paths: vec![],
+        }
+    }
+}
+
+/// Runs a program and restarts it whenever one of its local modules, `#include`d files or its import map changes
+pub async fn watch(input: ScriptSource, args: Vec<String>, options: RuntimeOptions, inspector_options: InspectorOptions, watch_options: WatchOptions) -> Result<(), AnyError> {
+    crate::init_v8();
+
+    let args0 = crate::get_args0(&input)?;
+    let inspector = crate::create_inspector_server(&inspector_options)?;
+
+    let (changes_sender, mut changes) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) {
+                for path in event.paths {
+                    let _ = changes_sender.send(path);
+                }
+            }
+        }
+    })?;
+
+    // Watched even if the program fails before loading them, otherwise there might be nothing to watch
+    let entry = match &input {
+        ScriptSource::File(_) => Some(PathBuf::from(&args0)),
+        ScriptSource::FileOrURL(_) if deno_core::url::Url::parse(&args0).is_err() => Some(PathBuf::from(&args0)),
+        _ => None
+    };
+    let import_map = match &options.import_map_source {
+        Some(source) => resolve_maybe_url(source).ok().and_then(|url| url.to_file_path().ok()),
+        None => entry.as_ref().map(|entry| entry.with_file_name("imports.json"))
+    };
+    let roots: Vec<PathBuf> = entry.into_iter().chain(import_map).chain(watch_options.paths.iter().cloned()).collect();
+
+    loop {
+        if watch_options.clear_screen {
+            eprint!("\x1b[2J\x1b[3J\x1b[H");
+        }
+
+        // Editors often replace files instead of writing to them, so parent directories are watched
+        let mut files = HashSet::new();
+        let mut dirs = HashSet::new();
+        let (files_sender, mut loaded_files) = mpsc::unbounded_channel();
+        for path in &roots {
+            let _ = files_sender.send(path.clone());
+        }
+
+        let program = crate::execute(input.clone(), args0.clone(), args.clone(), &options, inspector.clone(), inspector_options.wait, Some(WatchedFiles::new(files_sender)));
+        tokio::pin!(program);
+        let mut finished = false;
+
+        let changed = loop {
+            tokio::select! {
+                result = &mut program, if !finished => {
+                    finished = true;
+                    if let Err(err) = result {
+                        eprintln!("\x1b[91;1merror\x1b[0m: {}", err);
+                    }
+                    eprintln!("\x1b[1mWatcher\x1b[0m Process finished, restarting on file change...");
+                },
+                Some(path) = loaded_files.recv() => {
+                    let path = canonicalize(&path);
+                    if let Some(dir) = path.parent().map(PathBuf::from) {
+                        if !dirs.contains(&dir) && watcher.watch(&dir, RecursiveMode::NonRecursive).is_ok() {
+                            dirs.insert(dir);
+                        }
+                    }
+                    files.insert(path);
+                },
+                Some(path) = changes.recv() => if files.contains(&canonicalize(&path)) {
+                    break path;
+                }
+            }
+        };
+
+        // Wait for changes to settle before tearing the program down
+        loop {
+            tokio::select! {
+                Some(_) = changes.recv() => {},
+                _ = tokio::time::sleep(watch_options.debounce) => break
+            }
+        }
+
+        for dir in &dirs {
+            let _ = watcher.unwatch(dir);
+        }
+
+        if !watch_options.clear_screen {
+            eprintln!("\x1b[1mWatcher\x1b[0m File change detected, restarting: {}", changed.display());
+        }
+    }
+}
+
+/// Resolves symlinks in the directory of `path` so module paths, include paths and event paths compare equal,
+/// the file itself may not exist while it is being replaced
+fn canonicalize(path: &Path) -> PathBuf {
+    match (path.parent().and_then(|dir| dir.canonicalize().ok()), path.file_name()) {
+        (Some(dir), Some(name)) => dir.join(name),
+        _ => path.to_path_buf()
+    }
+}
@@ -2,8 +2,7 @@
 // Copyright 2018-2024 the Deno authors. All rights reserved. MIT license.
 
 /// Indicates how cached source files should be handled.
-#[allow(unused)]
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
 pub enum CacheSetting {
   /// Only the cached files should be used.  Any files not in the cache will
   /// error.  This is the equivalent of `--cached-only` in the CLI.
@@ -21,5 +20,6 @@ pub enum CacheSetting {
   RespectHeaders,
   /// The cached source files should be used for local modules.  This is the
   /// default behavior of the CLI.
+  #[default]
   Use,
 }
\ No newline at end of file
@@ -0,0 +1,258 @@
+use deno_runtime::deno_core;
+use deno_core::ModuleSpecifier;
+use deno_core::serde_json;
+
+use deno_ast::MediaType;
+use deno_ast::swc::ast::{AssignExpr,AssignTarget,CallExpr,Callee,Expr,Lit,MemberExpr,MemberProp,ModuleItem,Program,Prop,PropName,PropOrSpread,SimpleAssignTarget,TryStmt};
+use deno_ast::swc::visit::{Visit,VisitWith};
+
+use std::path::Path;
+
+/// What a CommonJS module requires and exports, found the way cjs-module-lexer does so
+/// ES modules can import its properties by name
+#[derive(Default)]
+struct CjsAnalyzer {
+    requires: Vec<String>,
+    /// Required inside a `try` block, usually optional dependencies whose absence is handled
+    optional_requires: Vec<String>,
+    exports: Vec<String>,
+    /// Modules whose exports are all exported again, as with `module.exports = require("...")`
+    reexports: Vec<String>,
+    try_depth: usize,
+}
+
+/// Wraps a CommonJS module in an ES module that runs it with `require`, `module` and `exports`. The
+/// specifiers it requires are imported up front since `require` is synchronous, except those that
+/// `is_resolvable` rejects which throw when required. Specifiers only required inside `try` blocks are
+/// imported dynamically and throw when required if they failed to load, so optional dependencies stay optional.
+/// Returns `None` for ES modules
+///
+/// Unlike in Node, the module runs in strict mode since it is part of an ES module, and circular requires
+/// between CommonJS modules are not supported because each module's requires are loaded before it runs
+pub fn wrap_commonjs(specifier: &ModuleSpecifier, code: &str, is_resolvable: impl Fn(&str) -> bool) -> Option<String> {
+    let mut analyzer = CjsAnalyzer::default();
+
+    // Modules that fail to parse, such as ones that return at the top level, still run but cannot be analyzed
+    if let Ok(parsed) = deno_ast::parse_program(deno_ast::ParseParams {
+        specifier: specifier.clone(),
+        text: code.into(),
+        media_type: MediaType::JavaScript,
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    }) {
+        match parsed.program_ref() {
+            Program::Module(module) if module.body.iter().any(|item| matches!(item, ModuleItem::ModuleDecl(_))) => return None,
+            Program::Module(module) => module.visit_with(&mut analyzer),
+            Program::Script(script) => script.visit_with(&mut analyzer)
+        }
+    }
+
+    let path = specifier.to_file_path().unwrap_or_default();
+    let filename = json_string(&path.display().to_string());
+    let dirname = json_string(&path.parent().map(Path::display).map(|dir| dir.to_string()).unwrap_or_default());
+
+    let mut requires: Vec<&String> = vec![];
+    for specifier in &analyzer.requires {
+        if !requires.contains(&specifier) && is_resolvable(specifier) {
+            requires.push(specifier);
+        }
+    }
+
+    let mut optional_requires: Vec<&String> = vec![];
+    for specifier in &analyzer.optional_requires {
+        if !requires.contains(&specifier) && !optional_requires.contains(&specifier) && is_resolvable(specifier) {
+            optional_requires.push(specifier);
+        }
+    }
+
+    let mut prelude = vec![];
+    let mut modules = vec![];
+    for (i, request) in requires.iter().enumerate() {
+        let literal = json_string(request);
+        if request.ends_with(".json") {
+            prelude.push(format!("import __sjs_require{i} from {literal} with {{ type: \"json\" }};"));
+            modules.push(format!("{literal}: {{ \"module.exports\": __sjs_require{i} }}"));
+        } else {
+            prelude.push(format!("import * as __sjs_require{i} from {literal};"));
+            modules.push(format!("{literal}: __sjs_require{i}"));
+        }
+    }
+    if !optional_requires.is_empty() {
+        let imports: Vec<String> = optional_requires.iter().map(|request| {
+            let literal = json_string(request);
+            if request.ends_with(".json") {
+                format!("import({literal}, {{ with: {{ type: \"json\" }} }}).then((json) => ({{ \"module.exports\": json.default }}), () => null)")
+            } else {
+                format!("import({literal}).catch(() => null)")
+            }
+        }).collect();
+        let names: Vec<String> = (0..optional_requires.len()).map(|i| format!("__sjs_optional{i}")).collect();
+        prelude.push(format!("const [{}] = await Promise.all([{}]);", names.join(", "), imports.join(", ")));
+        modules.extend(optional_requires.iter().zip(&names).map(|(request, name)| format!("{}: {}", json_string(request), name)));
+    }
+    for reexport in analyzer.reexports.iter().filter(|specifier| requires.contains(specifier) && !specifier.ends_with(".json")) {
+        prelude.push(format!("export * from {};", json_string(reexport)));
+    }
+
+    // Node globals are passed in like the module wrapper arguments so packages work without `--node-globals`
+    prelude.push(String::from("import __sjs_process from \"node:process\"; import { Buffer as __sjs_Buffer } from \"node:buffer\";"));
+    prelude.push(format!("const __sjs_modules = {{ {} }};", modules.join(", ")));
+    prelude.push(String::from(concat!(
+        "function require(specifier) { ",
+        "if (!Object.hasOwn(__sjs_modules, specifier) || __sjs_modules[specifier] === null) { const error = new Error(`Cannot find module '${specifier}'`); error.code = \"MODULE_NOT_FOUND\"; throw error; } ",
+        "const namespace = __sjs_modules[specifier]; ",
+        "return \"module.exports\" in namespace ? namespace[\"module.exports\"] : namespace; ",
+        "}"
+    )));
+    prelude.push(format!("const module = {{ exports: {{}}, id: {filename}, filename: {filename}, loaded: false, require }};"));
+
+    let mut names: Vec<&String> = analyzer.exports.iter().filter(|name| !matches!(name.as_str(), "default" | "module.exports")).collect();
+    names.sort();
+    names.dedup();
+    let bindings: Vec<String> = names.iter().enumerate().map(|(i, name)| format!("{}: __sjs_export{}", json_string(name), i)).collect();
+    let exports: Vec<String> = names.iter().enumerate().map(|(i, name)| format!("__sjs_export{} as {}", i, json_string(name))).collect();
+
+    // The prelude is kept to the first line so line numbers in the module stay the same, and a hashbang is
+    // commented out since it is only allowed at the very start
+    let code = match code.strip_prefix("#!") {
+        Some(code) => format!("//{}", code),
+        None => code.to_string()
+    };
+    Some(format!(
+        "{} (function (exports, require, module, __filename, __dirname, process, Buffer, global) {{{}\n}}).call(module.exports, module.exports, require, module, {}, {}, __sjs_process, __sjs_Buffer, globalThis);\n\
+        module.loaded = true;\n\
+        const __sjs_exports = module.exports;\n\
+        const {{ {} }} = __sjs_exports ?? {{}};\n\
+        export {{ __sjs_exports as default, __sjs_exports as \"module.exports\"{}{} }};\n",
+        prelude.join(" "), code, filename, dirname,
+        bindings.join(", "),
+        if exports.is_empty() { "" } else { ", " }, exports.join(", ")
+    ))
+}
+
+fn json_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap()
+}
+
+/// The specifier of a `require("...")` call with a literal argument
+fn require_specifier(call: &CallExpr) -> Option<String> {
+    let Callee::Expr(callee) = &call.callee else {
+        return None;
+    };
+    match (&**callee, call.args.as_slice()) {
+        (Expr::Ident(ident), [arg]) if &*ident.sym == "require" => match &*arg.expr {
+            Expr::Lit(Lit::Str(specifier)) => Some(specifier.value.to_string()),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+fn member_name(prop: &MemberProp) -> Option<String> {
+    match prop {
+        MemberProp::Ident(ident) => Some(ident.sym.to_string()),
+        MemberProp::Computed(computed) => match &*computed.expr {
+            Expr::Lit(Lit::Str(name)) => Some(name.value.to_string()),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+fn prop_name(name: &PropName) -> Option<String> {
+    match name {
+        PropName::Ident(ident) => Some(ident.sym.to_string()),
+        PropName::Str(name) => Some(name.value.to_string()),
+        _ => None
+    }
+}
+
+fn is_module_exports(member: &MemberExpr) -> bool {
+    matches!(&*member.obj, Expr::Ident(ident) if &*ident.sym == "module") && member_name(&member.prop).as_deref() == Some("exports")
+}
+
+/// Either `exports` or `module.exports`
+fn is_exports(expr: &Expr) -> bool {
+    match expr {
+        Expr::Ident(ident) => &*ident.sym == "exports",
+        Expr::Member(member) => is_module_exports(member),
+        _ => false
+    }
+}
+
+impl Visit for CjsAnalyzer {
+    fn visit_call_expr(&mut self, call: &CallExpr) {
+        if let Some(specifier) = require_specifier(call) {
+            if self.try_depth > 0 {
+                self.optional_requires.push(specifier);
+            } else {
+                self.requires.push(specifier);
+            }
+        }
+
+        if let Callee::Expr(callee) = &call.callee {
+            let name = match &**callee {
+                Expr::Ident(ident) => Some(ident.sym.to_string()),
+                Expr::Member(member) => member_name(&member.prop).map(|prop| match &*member.obj {
+                    Expr::Ident(ident) => format!("{}.{}", ident.sym, prop),
+                    _ => prop
+                }),
+                _ => None
+            };
+            match (name.as_deref(), call.args.as_slice()) {
+                // Object.defineProperty(exports, "name", { ... })
+                (Some("Object.defineProperty"), [target, name, ..]) if is_exports(&target.expr) => if let Expr::Lit(Lit::Str(name)) = &*name.expr {
+                    self.exports.push(name.value.to_string());
+                },
+                // Re-exports emitted by TypeScript, __exportStar(require("..."), exports)
+                (Some(name), [module, ..]) if name.ends_with("__exportStar") || name.ends_with("__export") => if let Expr::Call(module) = &*module.expr {
+                    if let Some(specifier) = require_specifier(module) {
+                        self.reexports.push(specifier);
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        call.visit_children_with(self);
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &TryStmt) {
+        self.try_depth += 1;
+        stmt.block.visit_with(self);
+        self.try_depth -= 1;
+        stmt.handler.visit_with(self);
+        stmt.finalizer.visit_with(self);
+    }
+
+    fn visit_assign_expr(&mut self, assign: &AssignExpr) {
+        if let AssignTarget::Simple(SimpleAssignTarget::Member(member)) = &assign.left {
+            if is_module_exports(member) {
+                match &*assign.right {
+                    Expr::Call(call) => if let Some(specifier) = require_specifier(call) {
+                        self.reexports.push(specifier);
+                    },
+                    Expr::Object(object) => for prop in &object.props {
+                        let PropOrSpread::Prop(prop) = prop else {
+                            continue;
+                        };
+                        let name = match &**prop {
+                            Prop::Shorthand(ident) => Some(ident.sym.to_string()),
+                            Prop::KeyValue(kv) => prop_name(&kv.key),
+                            Prop::Method(method) => prop_name(&method.key),
+                            Prop::Getter(getter) => prop_name(&getter.key),
+                            _ => None
+                        };
+                        self.exports.extend(name);
+                    },
+                    _ => {}
+                }
+            } else if is_exports(&member.obj) {
+                self.exports.extend(member_name(&member.prop));
+            }
+        }
+
+        assign.visit_children_with(self);
+    }
+}
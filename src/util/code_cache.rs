@@ -0,0 +1,50 @@
+use deno_runtime::deno_core;
+use deno_core::ModuleSpecifier;
+use deno_runtime::code_cache::{CodeCache,CodeCacheType};
+
+use std::fs;
+use std::hash::Hasher;
+use std::path::PathBuf;
+
+use crate::util::hash;
+
+/// V8 code caches stored as files, each starting with the hash of the source it was created from
+pub struct SJSCodeCache {
+    dir: PathBuf,
+}
+
+impl SJSCodeCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, specifier: &ModuleSpecifier, code_cache_type: CodeCacheType) -> PathBuf {
+        let code_cache_type = match code_cache_type {
+            CodeCacheType::EsModule => "module",
+            CodeCacheType::Script => "script",
+        };
+        self.dir.join(hash(format!("{}:{}", code_cache_type, specifier)))
+    }
+}
+
+/// Hashes the source a code cache is created from, a cache is only used if the source still matches
+pub fn source_hash(source: &[u8]) -> u64 {
+    let mut hasher = twox_hash::XxHash64::default();
+    hasher.write(source);
+    hasher.finish()
+}
+
+impl CodeCache for SJSCodeCache {
+    fn get_sync(&self, specifier: &ModuleSpecifier, code_cache_type: CodeCacheType, source_hash: u64) -> Option<Vec<u8>> {
+        let mut data = fs::read(self.path(specifier, code_cache_type)).ok()?;
+        if data.len() < 8 || data[..8] != source_hash.to_le_bytes() {
+            return None;
+        }
+        Some(data.split_off(8))
+    }
+
+    fn set_sync(&self, specifier: ModuleSpecifier, code_cache_type: CodeCacheType, source_hash: u64, data: &[u8]) {
+        // A missing cache only slows down the next start, so errors are ignored
+        let _ = fs::create_dir_all(&self.dir).and_then(|_| fs::write(self.path(&specifier, code_cache_type), [&source_hash.to_le_bytes()[..], data].concat()));
+    }
+}
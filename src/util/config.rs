@@ -0,0 +1,162 @@
+use deno_runtime::deno_core;
+use deno_core::error::{generic_error,AnyError};
+use deno_core::serde_json;
+
+use serde::Deserialize;
+
+use std::fs;
+use std::path::{Path,PathBuf};
+
+use crate::util::CacheSetting;
+use crate::util::path::ToAbsolutePath as _;
+use crate::util::url::resolve_maybe_url;
+
+pub const CONFIG_FILE_NAME: &str = "sjs.json";
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum CacheMode {
+    Use,
+    Reload,
+    Only,
+    RespectHeaders
+}
+
+/// Project defaults read from an `sjs.json` file, command line flags take precedence over these
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Config {
+    /// The config file this was read from, relative paths in the file are resolved against its directory
+    #[serde(skip)]
+    pub path: PathBuf,
+    #[serde(default)]
+    pub macros: Vec<String>,
+    #[serde(default)]
+    pub include_paths: Vec<String>,
+    pub import_map: Option<String>,
+    pub remote: Option<bool>,
+    cache: Option<CacheMode>,
+    /// Starts the inspector on this port without waiting for a debugger
+    pub port: Option<u16>,
+    /// Names from `deno_runtime::UNSTABLE_GRANULAR_FLAGS` to enable in addition to the defaults
+    #[serde(default)]
+    pub unstable: Vec<String>,
+    /// The registry `npm:` packages are downloaded from
+    pub npm_registry: Option<String>,
+    /// Exposes Node's `process`, `Buffer` and `global` globals
+    pub node_globals: Option<bool>,
+}
+
+impl Config {
+    /// Reads and validates a config file
+    pub fn load(path: &Path) -> Result<Self, AnyError> {
+        let text = fs::read_to_string(path).map_err(|x| generic_error(format!("{}: {}", path.display(), x)))?;
+        let mut config: Config = serde_json::from_str(&text).map_err(|x| generic_error(format!("{}: {}", path.display(), x)))?;
+        config.path = path.absolute()?;
+
+        if let Some(name) = config.unstable.iter().find(|name| !deno_runtime::UNSTABLE_GRANULAR_FLAGS.iter().any(|(flag_name, _, _)| *flag_name == name.as_str())) {
+            return Err(generic_error(format!("{}: Unknown unstable feature '{}'", path.display(), name)));
+        }
+
+        let dir = config.path.parent().map(Path::to_path_buf).unwrap_or_default();
+        for include_path in config.include_paths.iter_mut() {
+            *include_path = dir.join(&*include_path).to_string_lossy().into_owned();
+        }
+        if let Some(import_map) = config.import_map.as_mut().filter(|import_map| deno_core::url::Url::parse(import_map).is_err()) {
+            *import_map = dir.join(&*import_map).to_string_lossy().into_owned();
+        }
+
+        Ok(config)
+    }
+
+    /// Looks for a config file in the directory of `entry` and each of its parents, remote and
+    /// in memory entries start from the current directory instead
+    pub fn find(entry: Option<&str>) -> Result<Option<Self>, AnyError> {
+        let start = match entry.map(resolve_maybe_url).transpose()? {
+            Some(url) if url.scheme() == "file" => {
+                let path = url.to_file_path().map_err(|_| generic_error(format!("{}: Invalid file path", url)))?;
+                if path.is_dir() { path } else { path.parent().map(Path::to_path_buf).unwrap_or(path) }
+            },
+            _ => std::env::current_dir()?
+        };
+
+        match start.ancestors().map(|dir| dir.join(CONFIG_FILE_NAME)).find(|path| path.is_file()) {
+            Some(path) => Ok(Some(Self::load(&path)?)),
+            None => Ok(None)
+        }
+    }
+
+    pub fn cache_setting(&self) -> Option<CacheSetting> {
+        self.cache.map(|cache| match cache {
+            CacheMode::Use => CacheSetting::Use,
+            CacheMode::Reload => CacheSetting::ReloadAll,
+            CacheMode::Only => CacheSetting::Only,
+            CacheMode::RespectHeaders => CacheSetting::RespectHeaders
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_util::TempDir;
+
+    fn write_config(dir: &TempDir, text: &str) -> PathBuf {
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn resolves_paths_against_the_config_file() {
+        let dir = TempDir::new();
+        let path = write_config(&dir, r#"{ "includePaths": ["include", "/abs"], "importMap": "imports.json", "cache": "only" }"#);
+
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.path, path);
+        assert_eq!(config.include_paths, vec![dir.path().join("include").to_string_lossy().into_owned(), String::from("/abs")]);
+        assert_eq!(config.import_map, Some(dir.path().join("imports.json").to_string_lossy().into_owned()));
+        assert!(matches!(config.cache_setting(), Some(CacheSetting::Only)));
+    }
+
+    #[test]
+    fn keeps_remote_import_maps() {
+        let dir = TempDir::new();
+        let path = write_config(&dir, r#"{ "importMap": "https://example.com/imports.json" }"#);
+
+        assert_eq!(Config::load(&path).unwrap().import_map.as_deref(), Some("https://example.com/imports.json"));
+    }
+
+    #[test]
+    fn checks_unstable_features() {
+        let dir = TempDir::new();
+        let (name, _, _) = deno_runtime::UNSTABLE_GRANULAR_FLAGS[0];
+        let path = write_config(&dir, &format!(r#"{{ "unstable": ["{}"] }}"#, name));
+        assert_eq!(Config::load(&path).unwrap().unstable, vec![name.to_string()]);
+
+        let path = write_config(&dir, r#"{ "unstable": ["not-a-feature"] }"#);
+        let err = Config::load(&path).unwrap_err();
+        assert!(err.to_string().contains("Unknown unstable feature 'not-a-feature'"));
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        let dir = TempDir::new();
+        let path = write_config(&dir, r#"{ "imports": {} }"#);
+
+        assert!(Config::load(&path).is_err());
+    }
+
+    #[test]
+    fn finds_config_in_parent_directories() {
+        let dir = TempDir::new();
+        let path = write_config(&dir, "{}");
+        let nested = dir.path().join("src").join("nested");
+        fs::create_dir_all(&nested).unwrap();
+        let entry = nested.join("main.ts");
+        fs::write(&entry, "").unwrap();
+
+        let config = Config::find(Some(&entry.to_string_lossy())).unwrap().unwrap();
+        assert_eq!(config.path, path);
+    }
+}
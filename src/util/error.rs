@@ -0,0 +1,51 @@
+use deno_runtime::deno_core::ModuleSpecifier;
+use deno_runtime::deno_core::error::AnyError;
+
+use thiserror::Error;
+
+/// Errors sjs itself reports rather than panicking. Library functions return `AnyError` since scripts can
+/// throw anything, these can be recovered with `AnyError::downcast_ref::<SjsError>()`
+#[derive(Error, Debug)]
+pub enum SjsError {
+    /// The program to run is not a valid file path or URL
+    #[error("{input}: {reason}")]
+    InvalidSource {
+        input: String,
+        reason: String,
+    },
+    /// An import map given explicitly could not be fetched or parsed
+    #[error("{specifier}: {error}")]
+    ImportMap {
+        specifier: String,
+        error: AnyError,
+    },
+    /// A module could not be fetched, including `npm:` packages that could not be installed
+    #[error("{specifier}: {error}")]
+    Fetch {
+        specifier: ModuleSpecifier,
+        error: AnyError,
+    },
+    /// mtsc failed to compile a module
+    #[error("{specifier}: Failed to compile script")]
+    Compile {
+        specifier: ModuleSpecifier,
+    },
+    /// A web worker could not be set up, it fails to load its main module with this instead of running
+    #[error("{specifier}: {error}")]
+    WorkerStartup {
+        specifier: ModuleSpecifier,
+        error: AnyError,
+    },
+    /// A script called an unstable API that was not enabled, the worker that called it is stopped
+    #[error("Unstable API '{0}' is not supported!")]
+    UnsupportedUnstableApi(String),
+}
+
+impl SjsError {
+    pub(crate) fn invalid_source(input: &str, reason: impl ToString) -> Self {
+        Self::InvalidSource {
+            input: input.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
@@ -5,6 +5,8 @@ use crate::util::cache_settings::CacheSetting;
 
 use crate::util::auth_tokens::{AuthToken,AuthTokens};
 
+use crate::util::lockfile::Lockfile;
+
 use crate::util::http_util;
 use crate::util::http_util::{resolve_redirect_from_response,CacheSemantics,HeadersMap,HttpClient};
 
@@ -156,6 +158,9 @@ pub struct FileFetcher {
   http_cache: Arc<dyn HttpCache>,
   http_client: Arc<HttpClient>,
   blob_store: Arc<BlobStore>,
+  /// Remote specifiers fetched from the network rather than the cache, shared between clones
+  downloads: Arc<Mutex<Vec<ModuleSpecifier>>>,
+  lockfile: Option<Arc<Lockfile>>,
 }
 
 #[allow(unused)]
@@ -175,13 +180,35 @@ impl FileFetcher {
       http_cache,
       http_client,
       blob_store,
+      downloads: Default::default(),
+      lockfile: None,
     }
   }
 
+  /// Checks remote files against a lockfile, whether they came from the network or the cache
+  pub fn set_lockfile(&mut self, lockfile: Option<Arc<Lockfile>>) {
+    self.lockfile = lockfile;
+  }
+
+  /// The lockfile remote files are checked against, shared with the npm resolver so both record into one file
+  pub fn lockfile(&self) -> Option<Arc<Lockfile>> {
+    self.lockfile.clone()
+  }
+
   pub fn cache_setting(&self) -> &CacheSetting {
     &self.cache_setting
   }
 
+  /// The store `blob:` URLs are fetched from, workers must share it for scripts to import object URLs they create
+  pub fn blob_store(&self) -> &Arc<BlobStore> {
+    &self.blob_store
+  }
+
+  /// The remote specifiers downloaded so far, in the order they were written to the cache
+  pub fn downloads(&self) -> Vec<ModuleSpecifier> {
+    self.downloads.lock().clone()
+  }
+
   /// Fetch cached remote file.
   ///
   /// This is a recursive operation if source file has redirections.
@@ -371,6 +398,7 @@ impl FileFetcher {
             file_fetcher
               .http_cache
               .set(&specifier, headers.clone(), &bytes)?;
+            file_fetcher.downloads.lock().push(specifier.clone());
             Ok(File {
               specifier,
               maybe_headers: Some(headers),
@@ -494,6 +522,9 @@ impl FileFetcher {
           options.maybe_cache_setting.unwrap_or(&self.cache_setting),
         )
         .await;
+      if let (Ok(file), Some(lockfile)) = (&result, &self.lockfile) {
+        lockfile.check_or_insert(&file.specifier, &file.source)?;
+      }
       if let Ok(file) = &result {
         self.cache.insert(specifier.clone(), file.clone());
       }
@@ -523,6 +554,11 @@ impl FileFetcher {
   pub fn insert_cached(&self, file: File) -> Option<File> {
     self.cache.insert(file.specifier.clone(), file)
   }
+
+  /// Caches `file` for a specifier that redirects to it, like remote modules that were fetched before
+  pub fn insert_redirect(&self, specifier: ModuleSpecifier, file: File) -> Option<File> {
+    self.cache.insert(specifier, file)
+  }
 }
 
 #[derive(Debug, Eq, PartialEq)]
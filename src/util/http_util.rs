@@ -262,6 +262,34 @@ impl HttpClient {
   ) -> Result<reqwest::RequestBuilder, AnyError> {
     Ok(self.client()?.get(url))
   }
+
+  /// Downloads the body of `url`, following redirects. Used for files that
+  /// are not modules and so do not go through the http cache.
+  pub async fn download(
+    &self,
+    url: Url,
+    maybe_accept: Option<&str>,
+  ) -> Result<Vec<u8>, AnyError> {
+    let mut url = url;
+    for _ in 0..10 {
+      let mut request = self.get_no_redirect(url.clone())?;
+      if let Some(accept) = maybe_accept {
+        request = request.header(reqwest::header::ACCEPT, accept);
+      }
+      let response = request.send().await?;
+      if response.status().is_redirection() {
+        url = resolve_redirect_from_response(&url, &response)?;
+      } else if response.status().is_success() {
+        return get_response_body(response).await;
+      } else {
+        return Err(generic_error(format!(
+          "Bad response: {} for \"{url}\"",
+          response.status()
+        )));
+      }
+    }
+    Err(generic_error(format!("Too many redirects for \"{url}\"")))
+  }
 }
 
 pub async fn get_response_body(
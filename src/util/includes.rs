@@ -0,0 +1,108 @@
+use std::fs;
+use std::path::{Path,PathBuf};
+
+/// A line of a source after its `#include`s are expanded in place
+pub struct ExpandedLine {
+    /// The included file the line came from, `None` for the source itself
+    pub file: Option<PathBuf>,
+    /// Zero based line number within that file
+    pub line: usize,
+    pub text: String,
+}
+
+/// Finds the local files a source pulls in with `#include`, following nested includes
+///
+/// Quoted includes are searched for next to the including file first, angle bracket
+/// includes only search the include paths, the same order mtsc's preprocessor uses.
+/// Includes that cannot be found are skipped since mtsc will report them when compiling.
+pub fn find_includes(source: &str, dir: Option<&Path>, include_paths: &[String]) -> Vec<PathBuf> {
+    let mut found = vec![];
+    collect(source, dir, include_paths, &mut found);
+    found
+}
+
+fn collect(source: &str, dir: Option<&Path>, include_paths: &[String], found: &mut Vec<PathBuf>) {
+    for (name, quoted) in source.lines().filter_map(parse_include) {
+        let Some(path) = resolve_include(name, quoted, dir, include_paths) else {
+            continue;
+        };
+        if found.contains(&path) {
+            continue;
+        }
+        found.push(path.clone());
+
+        if let Ok(source) = fs::read_to_string(&path) {
+            collect(&source, path.parent(), include_paths, found);
+        }
+    }
+}
+
+/// Replaces each `#include` line with the lines of the file it includes, recursively, remembering where every line came from
+pub fn expand_includes(source: &str, dir: Option<&Path>, include_paths: &[String]) -> Vec<ExpandedLine> {
+    let mut lines = vec![];
+    expand(source, None, dir, include_paths, &mut vec![], &mut lines);
+    lines
+}
+
+fn expand(source: &str, file: Option<&Path>, dir: Option<&Path>, include_paths: &[String], stack: &mut Vec<PathBuf>, lines: &mut Vec<ExpandedLine>) {
+    for (line, text) in source.lines().enumerate() {
+        let included = parse_include(text)
+            .and_then(|(name, quoted)| resolve_include(name, quoted, dir, include_paths))
+            .filter(|path| !stack.contains(path))
+            .and_then(|path| fs::read_to_string(&path).ok().map(|source| (path, source)));
+
+        match included {
+            Some((path, source)) => {
+                stack.push(path.clone());
+                expand(&source, Some(&path), path.parent(), include_paths, stack, lines);
+                stack.pop();
+            },
+            None => lines.push(ExpandedLine {
+                file: file.map(Path::to_path_buf),
+                line,
+                text: text.to_string()
+            })
+        }
+    }
+}
+
+fn resolve_include(name: &str, quoted: bool, dir: Option<&Path>, include_paths: &[String]) -> Option<PathBuf> {
+    dir.filter(|_| quoted).into_iter().map(Path::to_path_buf)
+        .chain(include_paths.iter().map(PathBuf::from))
+        .map(|include_path| include_path.join(name))
+        .filter_map(|path| path.canonicalize().ok())
+        .find(|path| path.is_file())
+}
+
+/// Parses `#include "name"` or `#include <name>`, returning the name and whether it was quoted
+fn parse_include(line: &str) -> Option<(&str, bool)> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start().strip_prefix("include")?.trim_start();
+    if let Some(rest) = rest.strip_prefix('"') {
+        Some((&rest[..rest.find('"')?], true))
+    } else if let Some(rest) = rest.strip_prefix('<') {
+        Some((&rest[..rest.find('>')?], false))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_includes() {
+        assert_eq!(parse_include("#include \"util.ts\""), Some(("util.ts", true)));
+        assert_eq!(parse_include("  #  include <lib/std.ts>"), Some(("lib/std.ts", false)));
+        assert_eq!(parse_include("#include \"util.ts\" // trailing"), Some(("util.ts", true)));
+    }
+
+    #[test]
+    fn ignores_other_lines() {
+        assert_eq!(parse_include("#define X 1"), None);
+        assert_eq!(parse_include("// #include \"util.ts\""), None);
+        assert_eq!(parse_include("#include util.ts"), None);
+        assert_eq!(parse_include("#include \"unterminated"), None);
+        assert_eq!(parse_include("#include <unterminated"), None);
+    }
+}
@@ -0,0 +1,331 @@
+use deno_runtime::deno_core;
+use deno_core::error::{generic_error,AnyError};
+
+use std::borrow::Cow;
+
+/// Media types of JSON modules that may contain comments and trailing commas, JSON5 also allows the rest of its syntax
+pub const JSONC_MIME_TYPES: [&str; 2] = ["application/jsonc", JSON5_MIME_TYPE];
+
+/// Extensions of JSON modules that may contain comments and trailing commas, JSON5 also allows the rest of its syntax
+pub const JSONC_EXTENSIONS: [&str; 2] = ["jsonc", JSON5_EXTENSION];
+
+pub const JSON5_MIME_TYPE: &str = "application/json5";
+
+pub const JSON5_EXTENSION: &str = "json5";
+
+/// Blanks out comments and trailing commas so the text parses as strict JSON. Everything else stays where it was,
+/// so parse errors point at the same line and column in the original text.
+pub fn strip_jsonc(text: &str) -> Cow<str> {
+    let bytes = text.as_bytes();
+    let mut out: Option<Vec<u8>> = None;
+    let mut blank = |range: std::ops::Range<usize>| {
+        let out = out.get_or_insert_with(|| bytes.to_vec());
+        for i in range {
+            if !matches!(out[i], b'\n' | b'\r') {
+                out[i] = b' ';
+            }
+        }
+    };
+
+    let mut last_comma = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                i += 1;
+                while i < bytes.len() {
+                    match bytes[i] {
+                        b'\\' => i += 2,
+                        b'"' => break,
+                        _ => i += 1
+                    }
+                }
+                i += 1;
+                last_comma = None;
+            },
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                let end = bytes[i..].iter().position(|&b| b == b'\n').map_or(bytes.len(), |n| i + n);
+                blank(i..end);
+                i = end;
+            },
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let end = bytes[i + 2..].windows(2).position(|w| w == b"*/").map_or(bytes.len(), |n| i + 2 + n + 2);
+                blank(i..end);
+                i = end;
+            },
+            b',' => {
+                last_comma = Some(i);
+                i += 1;
+            },
+            b'}' | b']' => {
+                if let Some(comma) = last_comma.take() {
+                    blank(comma..comma + 1);
+                }
+                i += 1;
+            },
+            b if b.is_ascii_whitespace() => i += 1,
+            _ => {
+                last_comma = None;
+                i += 1;
+            }
+        }
+    }
+
+    match out {
+        // Only whole characters inside comments were replaced, and only with ASCII
+        Some(out) => Cow::Owned(String::from_utf8(out).expect("JSONC stripping produced invalid UTF-8")),
+        None => Cow::Borrowed(text)
+    }
+}
+
+/// Converts JSON5 to JSON: unquoted keys, single quoted strings and their escapes, hexadecimal numbers, leading and
+/// trailing decimal points and explicit plus signs, along with comments and trailing commas. Line breaks outside of
+/// strings are kept so later parse errors point at the same line. `Infinity` and `NaN` have no JSON equivalent and
+/// are an error
+pub fn json5_to_json(text: &str) -> Result<String, AnyError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut last_comma = None;
+    let mut line = 1;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '/' if chars.get(i + 1) == Some(&'/') || chars.get(i + 1) == Some(&'*') => {
+                let end = skip_comment(&chars, i).ok_or_else(|| generic_error(format!("Unterminated comment at line {}", line)))?;
+                for &c in &chars[i..end] {
+                    if c == '\n' {
+                        out.push('\n');
+                        line += 1;
+                    }
+                }
+                i = end;
+            },
+            '"' | '\'' => {
+                i = json5_string(&chars, i, &mut out, &mut line)?;
+                last_comma = None;
+            },
+            ',' => {
+                last_comma = Some(out.len());
+                out.push(',');
+                i += 1;
+            },
+            '}' | ']' => {
+                if let Some(comma) = last_comma.take() {
+                    out.replace_range(comma..comma + 1, " ");
+                }
+                out.push(c);
+                i += 1;
+            },
+            '\n' => {
+                out.push('\n');
+                line += 1;
+                i += 1;
+            },
+            // JSON5 allows any Unicode whitespace, JSON only ASCII
+            c if c.is_whitespace() || c == '\u{feff}' => {
+                out.push(if c == '\r' { c } else { ' ' });
+                i += 1;
+            },
+            c if c.is_ascii_digit() || matches!(c, '+' | '-' | '.') => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.' || (matches!(chars[i], '+' | '-') && matches!(chars[i - 1], 'e' | 'E') && !is_hex(&chars[start..i]))) {
+                    i += 1;
+                }
+                out.push_str(&json5_number(&chars[start..i].iter().collect::<String>(), line)?);
+                last_comma = None;
+            },
+            c if is_identifier_start(c) => {
+                let start = i;
+                while i < chars.len() && (is_identifier_start(chars[i]) || chars[i].is_ascii_digit()) {
+                    i += 1;
+                }
+                let identifier: String = chars[start..i].iter().collect();
+                let mut next = i;
+                while let Some(&c) = chars.get(next) {
+                    if c.is_whitespace() {
+                        next += 1;
+                    } else if c == '/' && matches!(chars.get(next + 1), Some('/' | '*')) {
+                        next = skip_comment(&chars, next).unwrap_or(chars.len());
+                    } else {
+                        break;
+                    }
+                }
+                match identifier.as_str() {
+                    _ if chars.get(next) == Some(&':') => out.push_str(&format!("\"{}\"", identifier)),
+                    "true" | "false" | "null" => out.push_str(&identifier),
+                    "Infinity" | "NaN" => return Err(generic_error(format!("{} cannot be represented in JSON at line {}", identifier, line))),
+                    _ => return Err(generic_error(format!("Unexpected identifier '{}' at line {}", identifier, line)))
+                }
+                last_comma = None;
+            },
+            _ => {
+                out.push(c);
+                last_comma = None;
+                i += 1;
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// The index after a `//` or `/* */` comment starting at `start`, `None` if a block comment is never closed
+fn skip_comment(chars: &[char], start: usize) -> Option<usize> {
+    if chars.get(start + 1) == Some(&'/') {
+        return Some(chars[start..].iter().position(|&c| c == '\n').map_or(chars.len(), |n| start + n));
+    }
+    chars[start + 2..].windows(2).position(|w| w == ['*', '/']).map(|n| start + 2 + n + 2)
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_hex(number: &[char]) -> bool {
+    let digits = match number.first() {
+        Some('+' | '-') => &number[1..],
+        _ => number
+    };
+    matches!(digits, ['0', 'x' | 'X', ..])
+}
+
+fn json5_number(number: &str, line: usize) -> Result<String, AnyError> {
+    let (sign, digits) = match number.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", number.strip_prefix('+').unwrap_or(number))
+    };
+    if digits == "Infinity" || digits == "NaN" {
+        return Err(generic_error(format!("{} cannot be represented in JSON at line {}", number, line)));
+    }
+    if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        let value = u128::from_str_radix(hex, 16).map_err(|_| generic_error(format!("Invalid number '{}' at line {}", number, line)))?;
+        return Ok(format!("{}{}", sign, value));
+    }
+    let (mantissa, exponent) = match digits.find(['e', 'E']) {
+        Some(i) => digits.split_at(i),
+        None => (digits, "")
+    };
+    let mantissa = mantissa.strip_suffix('.').unwrap_or(mantissa);
+    let zero = if mantissa.starts_with('.') { "0" } else { "" };
+    Ok(format!("{}{}{}{}", sign, zero, mantissa, exponent))
+}
+
+/// Copies a string starting at `start` as a double quoted JSON string, returning the index after it
+fn json5_string(chars: &[char], start: usize, out: &mut String, line: &mut usize) -> Result<usize, AnyError> {
+    let quote = chars[start];
+    let unterminated = |line: usize| generic_error(format!("Unterminated string at line {}", line));
+    out.push('"');
+    let mut i = start + 1;
+    loop {
+        let Some(&c) = chars.get(i) else {
+            return Err(unterminated(*line));
+        };
+        i += 1;
+        match c {
+            _ if c == quote => break,
+            '"' => out.push_str("\\\""),
+            '\n' => return Err(unterminated(*line)),
+            '\\' => {
+                let Some(&escaped) = chars.get(i) else {
+                    return Err(unterminated(*line));
+                };
+                i += 1;
+                match escaped {
+                    // Line continuations are left out of the value
+                    '\n' => *line += 1,
+                    '\r' => {
+                        *line += 1;
+                        if chars.get(i) == Some(&'\n') {
+                            i += 1;
+                        }
+                    },
+                    '\u{2028}' | '\u{2029}' => {},
+                    '"' => out.push_str("\\\""),
+                    'x' => {
+                        let hex: String = chars.get(i..i + 2).ok_or_else(|| unterminated(*line))?.iter().collect();
+                        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                            return Err(generic_error(format!("Invalid escape '\\x{}' at line {}", hex, line)));
+                        }
+                        out.push_str(&format!("\\u00{}", hex));
+                        i += 2;
+                    },
+                    'v' => out.push_str("\\u000b"),
+                    '0' => out.push_str("\\u0000"),
+                    'b' | 'f' | 'n' | 'r' | 't' | 'u' | '/' | '\\' => {
+                        out.push('\\');
+                        out.push(escaped);
+                    },
+                    // Any other character escapes itself
+                    _ => out.push(escaped)
+                }
+            },
+            _ => out.push(c)
+        }
+    }
+    out.push('"');
+    Ok(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use deno_core::serde_json;
+
+    #[test]
+    fn leaves_strict_json_borrowed() {
+        assert!(matches!(strip_jsonc(r#"{ "a": [1, 2] }"#), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn blanks_comments() {
+        assert_eq!(strip_jsonc("{ // note\n\"a\": /* x */ 1 }"), "{        \n\"a\":         1 }");
+        assert_eq!(strip_jsonc("1 /* unterminated"), "1                ");
+    }
+
+    #[test]
+    fn blanks_trailing_commas() {
+        assert_eq!(strip_jsonc("[1, 2,]"), "[1, 2 ]");
+        assert_eq!(strip_jsonc("{ \"a\": 1, // last\n}"), "{ \"a\": 1         \n}");
+    }
+
+    #[test]
+    fn converts_json5() {
+        let text = "// config\n{ unquoted: 'single \\'quoted\\' \"double\"', hex: 0xFF, half: .5, whole: 5., positive: +1, exp: 1e-3, list: [1, 2,], }";
+        let json: serde_json::Value = serde_json::from_str(&json5_to_json(text).unwrap()).unwrap();
+        assert_eq!(json, serde_json::json!({
+            "unquoted": "single 'quoted' \"double\"",
+            "hex": 255,
+            "half": 0.5,
+            "whole": 5,
+            "positive": 1,
+            "exp": 0.001,
+            "list": [1, 2]
+        }));
+    }
+
+    #[test]
+    fn converts_json5_escapes() {
+        let json: serde_json::Value = serde_json::from_str(&json5_to_json("['\\x41\\v\\0', 'line \\\ncontinued', '\\q']").unwrap()).unwrap();
+        assert_eq!(json, serde_json::json!(["A\u{b}\u{0}", "line continued", "q"]));
+    }
+
+    #[test]
+    fn keeps_json5_lines() {
+        assert_eq!(json5_to_json("{\n  a: 1, /* x\n y */\n}").unwrap().lines().count(), 4);
+    }
+
+    #[test]
+    fn rejects_json5_without_json_equivalent() {
+        assert!(json5_to_json("{ a: Infinity }").is_err());
+        assert!(json5_to_json("[-NaN]").is_err());
+        assert!(json5_to_json("[value]").is_err());
+        assert!(json5_to_json("['unterminated]").is_err());
+    }
+
+    #[test]
+    fn keeps_strings() {
+        let text = r#"{ "url": "https://example.com/*,]", "escaped": "\"//" }"#;
+        assert_eq!(strip_jsonc(text), text);
+    }
+}
@@ -0,0 +1,164 @@
+use deno_runtime::deno_core;
+use deno_core::ModuleSpecifier;
+use deno_core::error::{custom_error,generic_error,AnyError};
+use deno_core::parking_lot::Mutex;
+use deno_core::serde_json;
+
+use serde::{Deserialize,Serialize};
+use sha2::{Digest,Sha256};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+pub const LOCKFILE_NAME: &str = "sjs.lock";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LockfileContent {
+    version: String,
+    /// SHA-256 hashes of remote modules by their final specifier
+    remote: BTreeMap<String, String>,
+    /// Subresource integrity strings of npm package tarballs by `name@version`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    npm: BTreeMap<String, String>,
+}
+
+impl LockfileContent {
+    fn new() -> Self {
+        Self {
+            version: String::from("1"),
+            remote: BTreeMap::new(),
+            npm: BTreeMap::new(),
+        }
+    }
+}
+
+/// Records the hash of every remote file and npm package tarball the first time it is fetched and rejects any that change afterwards
+#[derive(Debug)]
+pub struct Lockfile {
+    path: PathBuf,
+    content: Mutex<LockfileContent>,
+}
+
+impl Lockfile {
+    /// Reads the lockfile at `path`, `overwrite` starts over with an empty one to record the current sources
+    pub fn new(path: PathBuf, overwrite: bool) -> Result<Self, AnyError> {
+        let content = match fs::read_to_string(&path) {
+            Ok(_) if overwrite => LockfileContent::new(),
+            Ok(text) => serde_json::from_str(&text).map_err(|x| generic_error(format!("{}: {}", path.display(), x)))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => LockfileContent::new(),
+            Err(err) => return Err(generic_error(format!("{}: {}", path.display(), err)))
+        };
+        let lockfile = Self {
+            path,
+            content: Mutex::new(content),
+        };
+        // Written even if nothing is fetched, so hashes of modules that are no longer used do not survive
+        if overwrite {
+            lockfile.write(&lockfile.content.lock())?;
+        }
+        Ok(lockfile)
+    }
+
+    fn write(&self, content: &LockfileContent) -> Result<(), AnyError> {
+        let json = serde_json::to_string_pretty(content)?;
+        fs::write(&self.path, json + "\n").map_err(|x| generic_error(format!("{}: {}", self.path.display(), x)))
+    }
+
+    /// Fails if `source` does not match the hash recorded for `specifier`, recording it if there is none
+    pub fn check_or_insert(&self, specifier: &ModuleSpecifier, source: &[u8]) -> Result<(), AnyError> {
+        let actual = format!("{:x}", Sha256::digest(source));
+        self.check_or_insert_entry(|content| &mut content.remote, &format!("The source code of \"{}\"", specifier), specifier.as_str(), actual)
+    }
+
+    /// Fails if the tarball `integrity` of the npm package `name@version` does not match the one recorded, recording it if there is none
+    pub fn check_or_insert_npm(&self, package: &str, integrity: &str) -> Result<(), AnyError> {
+        self.check_or_insert_entry(|content| &mut content.npm, &format!("The tarball of npm:{}", package), package, integrity.to_string())
+    }
+
+    fn check_or_insert_entry(&self, entries: fn(&mut LockfileContent) -> &mut BTreeMap<String, String>, what: &str, key: &str, actual: String) -> Result<(), AnyError> {
+        let mut content = self.content.lock();
+        match entries(&mut content).get(key) {
+            Some(expected) if expected == &actual => Ok(()),
+            Some(expected) => Err(custom_error("Integrity", format!(
+                "{} does not match the hash in {} (expected {}, found {}), rerun with --lock-write if this change is expected",
+                what, self.path.display(), expected, actual
+            ))),
+            None => {
+                entries(&mut content).insert(key.to_string(), actual);
+                self.write(&content)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_util::TempDir;
+
+    fn specifier() -> ModuleSpecifier {
+        ModuleSpecifier::parse("https://example.com/mod.ts").unwrap()
+    }
+
+    #[test]
+    fn records_and_checks_hashes() {
+        let dir = TempDir::new();
+        let path = dir.path().join(LOCKFILE_NAME);
+
+        let lockfile = Lockfile::new(path.clone(), false).unwrap();
+        assert!(!path.exists());
+        lockfile.check_or_insert(&specifier(), b"export {};").unwrap();
+        assert!(fs::read_to_string(&path).unwrap().contains("https://example.com/mod.ts"));
+
+        let lockfile = Lockfile::new(path.clone(), false).unwrap();
+        lockfile.check_or_insert(&specifier(), b"export {};").unwrap();
+        let err = lockfile.check_or_insert(&specifier(), b"export const changed = true;").unwrap_err();
+        assert!(err.to_string().contains("does not match the hash"));
+    }
+
+    #[test]
+    fn records_and_checks_npm_integrity() {
+        let dir = TempDir::new();
+        let path = dir.path().join(LOCKFILE_NAME);
+
+        Lockfile::new(path.clone(), false).unwrap().check_or_insert_npm("preact@10.5.0", "sha512-abc").unwrap();
+
+        let lockfile = Lockfile::new(path.clone(), false).unwrap();
+        lockfile.check_or_insert_npm("preact@10.5.0", "sha512-abc").unwrap();
+        lockfile.check_or_insert_npm("preact@10.6.0", "sha512-def").unwrap();
+        let err = lockfile.check_or_insert_npm("preact@10.5.0", "sha512-changed").unwrap_err();
+        assert!(err.to_string().contains("npm:preact@10.5.0"));
+    }
+
+    #[test]
+    fn overwrite_starts_over() {
+        let dir = TempDir::new();
+        let path = dir.path().join(LOCKFILE_NAME);
+
+        Lockfile::new(path.clone(), false).unwrap().check_or_insert(&specifier(), b"export {};").unwrap();
+
+        let lockfile = Lockfile::new(path.clone(), true).unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("https://example.com/mod.ts"));
+        lockfile.check_or_insert(&specifier(), b"export const changed = true;").unwrap();
+    }
+
+    #[test]
+    fn overwrite_writes_without_fetching() {
+        let dir = TempDir::new();
+        let path = dir.path().join(LOCKFILE_NAME);
+
+        Lockfile::new(path.clone(), true).unwrap();
+        assert!(path.is_file());
+    }
+
+    #[test]
+    fn rejects_invalid_lockfiles() {
+        let dir = TempDir::new();
+        let path = dir.path().join(LOCKFILE_NAME);
+        fs::write(&path, "not json").unwrap();
+
+        assert!(Lockfile::new(path.clone(), false).is_err());
+        assert!(Lockfile::new(path, true).is_ok());
+    }
+}
@@ -14,7 +14,39 @@ mod http_util;
 pub use http_util::HttpClient;
 
 mod module_loader;
-pub use module_loader::SJSModuleLoader;
+pub use module_loader::{SJSModuleLoader,LoadedModule,evaluate_raw_module};
+
+pub mod module_graph;
+
+mod wasm;
+
+pub mod includes;
+
+mod source_map;
+pub use source_map::{SourceMapStore,create_source_map};
+
+mod transpile_cache;
+pub use transpile_cache::TranspileCache;
+
+mod code_cache;
+pub use code_cache::SJSCodeCache;
+
+mod lockfile;
+pub use lockfile::{Lockfile,LOCKFILE_NAME};
+
+mod npm;
+pub use npm::{NpmResolver,NpmModuleFormat,DEFAULT_NPM_REGISTRY};
+
+mod cjs;
+
+pub mod jsonc;
+
+pub mod node;
+
+pub mod watch;
+
+mod config;
+pub use config::Config;
 
 pub mod path;
 
@@ -31,4 +63,10 @@ pub fn get_user_agent() -> &'static str {
     concat!("sjs/", env!("CARGO_PKG_VERSION"))
 }
 
-pub use deno_runtime::deno_core::error::AnyError;
\ No newline at end of file
+pub use deno_runtime::deno_core::error::AnyError;
+
+mod error;
+pub use error::SjsError;
+
+#[cfg(test)]
+pub(crate) mod test_util;
\ No newline at end of file
@@ -0,0 +1,231 @@
+use deno_runtime::deno_core;
+use deno_core::{ModuleSpecifier,ModuleLoader,ModuleType,RequestedModuleType,ResolutionKind};
+use deno_core::error::AnyError;
+
+use deno_ast::{MediaType,ParsedSource,StartSourcePos,SourceRangedForSpanned as _};
+use deno_ast::swc::ast::{CallExpr,Callee,Expr,Lit,ModuleDecl,ObjectLit,Prop,PropName,PropOrSpread,Str,Program};
+use deno_ast::swc::visit::{Visit,VisitWith};
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::util::{SJSModuleLoader,LoadedModule};
+use crate::util::wasm;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DependencyKind {
+    Static,
+    Dynamic
+}
+
+pub struct ModuleDependency {
+    /// The specifier as written in the importing module
+    pub specifier: String,
+    pub kind: DependencyKind,
+    pub module_type: RequestedModuleType,
+    /// Byte range of the specifier's string literal in the compiled code
+    pub range: Range<usize>,
+    pub resolved: Result<ModuleSpecifier, String>,
+}
+
+pub struct GraphModule {
+    /// The requested specifier, see `LoadedModule::file` for the final one
+    pub specifier: ModuleSpecifier,
+    pub module: Result<LoadedModule, String>,
+    pub dependencies: Vec<ModuleDependency>,
+}
+
+/// The modules reachable from a set of roots, loaded the same way the runtime would load them
+pub struct ModuleGraph {
+    pub roots: Vec<ModuleSpecifier>,
+    pub modules: Vec<GraphModule>,
+    indices: HashMap<ModuleSpecifier, usize>,
+}
+
+impl ModuleGraph {
+    /// Walks the graph from `roots` without executing anything, dynamic imports with literal
+    /// specifiers are only followed when `follow_dynamic` is set
+    pub async fn build(loader: &SJSModuleLoader, roots: Vec<ModuleSpecifier>, follow_dynamic: bool) -> Self {
+        let mut graph = Self {
+            roots: roots.clone(),
+            modules: vec![],
+            indices: HashMap::new()
+        };
+
+        let mut pending: Vec<(ModuleSpecifier, RequestedModuleType)> = roots.into_iter().rev().map(|root| (root, RequestedModuleType::None)).collect();
+
+        while let Some((specifier, module_type)) = pending.pop() {
+            if graph.indices.contains_key(&specifier) {
+                continue;
+            }
+
+            let (module, dependencies) = match load(loader, &specifier, module_type).await {
+                Ok((module, dependencies)) => (Ok(module), dependencies),
+                Err(err) => (Err(format!("{}", err)), vec![])
+            };
+
+            for dependency in dependencies.iter().rev() {
+                if let Ok(resolved) = &dependency.resolved {
+                    if dependency.kind == DependencyKind::Static || follow_dynamic {
+                        pending.push((resolved.clone(), dependency.module_type.clone()));
+                    }
+                }
+            }
+
+            graph.indices.insert(specifier.clone(), graph.modules.len());
+            graph.modules.push(GraphModule {
+                specifier,
+                module,
+                dependencies
+            });
+        }
+
+        graph
+    }
+
+    pub fn get(&self, specifier: &ModuleSpecifier) -> Option<&GraphModule> {
+        self.indices.get(specifier).map(|&i| &self.modules[i])
+    }
+
+    pub fn index_of(&self, specifier: &ModuleSpecifier) -> Option<usize> {
+        self.indices.get(specifier).copied()
+    }
+}
+
+async fn load(loader: &SJSModuleLoader, specifier: &ModuleSpecifier, module_type: RequestedModuleType) -> Result<(LoadedModule, Vec<ModuleDependency>), AnyError> {
+    // Nothing is run, so dynamic imports are loaded like the rest of the graph
+    let module = loader.load_module(specifier, module_type, false).await?;
+
+    let mut dependencies = match (&module.module_type, &module.code) {
+        (ModuleType::JavaScript, Some(code)) => analyze_dependencies(specifier, code)?,
+        (ModuleType::Wasm, _) => wasm::module_imports(&module.file.source)?.into_iter().map(|name| ModuleDependency {
+            specifier: name,
+            kind: DependencyKind::Static,
+            module_type: RequestedModuleType::None,
+            range: 0..0,
+            resolved: Err(String::new())
+        }).collect(),
+        _ => vec![]
+    };
+
+    // Imports are relative to the final specifier, as they are at runtime
+    for dependency in dependencies.iter_mut() {
+        dependency.resolved = loader.resolve(&dependency.specifier, module.file.specifier.as_str(), ResolutionKind::Import).map_err(|x| format!("{}", x));
+    }
+
+    Ok((module, dependencies))
+}
+
+pub fn parse_module(specifier: &ModuleSpecifier, code: &str) -> Result<ParsedSource, AnyError> {
+    Ok(deno_ast::parse_module(deno_ast::ParseParams {
+        specifier: specifier.clone(),
+        text: code.into(),
+        media_type: MediaType::JavaScript,
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    })?)
+}
+
+/// Finds the static imports and re-exports of compiled code along with any dynamic imports of literal specifiers
+pub fn analyze_dependencies(specifier: &ModuleSpecifier, code: &str) -> Result<Vec<ModuleDependency>, AnyError> {
+    let parsed = parse_module(specifier, code)?;
+    let mut collector = DependencyCollector {
+        start: parsed.text_info_lazy().range().start,
+        dependencies: vec![]
+    };
+    match parsed.program_ref() {
+        Program::Module(module) => module.visit_with(&mut collector),
+        Program::Script(script) => script.visit_with(&mut collector)
+    }
+    Ok(collector.dependencies)
+}
+
+/// Reads `type` from an import attributes object such as `{ type: "json" }`
+pub fn get_requested_module_type(attributes: Option<&ObjectLit>) -> RequestedModuleType {
+    let ty = attributes.into_iter().flat_map(|attributes| attributes.props.iter()).find_map(|prop| match prop {
+        PropOrSpread::Prop(prop) => match &**prop {
+            Prop::KeyValue(kv) => {
+                let key = match &kv.key {
+                    PropName::Ident(ident) => ident.sym.to_string(),
+                    PropName::Str(str) => str.value.to_string(),
+                    _ => return None
+                };
+                match &*kv.value {
+                    Expr::Lit(Lit::Str(value)) if key == "type" => Some(value.value.to_string()),
+                    _ => None
+                }
+            },
+            _ => None
+        },
+        _ => None
+    });
+
+    match ty.as_deref() {
+        None => RequestedModuleType::None,
+        Some("json") => RequestedModuleType::Json,
+        Some(ty) => RequestedModuleType::Other(ty.to_string().into())
+    }
+}
+
+/// Returns the literal specifier and import attributes of an `import()` call if it has one
+pub fn get_dynamic_import(call: &CallExpr) -> Option<(&Str, Option<&ObjectLit>)> {
+    if !matches!(call.callee, Callee::Import(_)) {
+        return None;
+    }
+    let Expr::Lit(Lit::Str(specifier)) = &*call.args.first()?.expr else {
+        return None;
+    };
+    let attributes = call.args.get(1).and_then(|options| match &*options.expr {
+        Expr::Object(options) => options.props.iter().find_map(|prop| match prop {
+            PropOrSpread::Prop(prop) => match &**prop {
+                Prop::KeyValue(kv) if matches!(&kv.key, PropName::Ident(ident) if &*ident.sym == "with") => match &*kv.value {
+                    Expr::Object(attributes) => Some(attributes),
+                    _ => None
+                },
+                _ => None
+            },
+            _ => None
+        }),
+        _ => None
+    });
+    Some((specifier, attributes))
+}
+
+struct DependencyCollector {
+    start: StartSourcePos,
+    dependencies: Vec<ModuleDependency>,
+}
+
+impl DependencyCollector {
+    fn push(&mut self, specifier: &Str, kind: DependencyKind, attributes: Option<&ObjectLit>) {
+        self.dependencies.push(ModuleDependency {
+            specifier: specifier.value.to_string(),
+            kind,
+            module_type: get_requested_module_type(attributes),
+            range: specifier.range().as_byte_range(self.start),
+            resolved: Err(String::new())
+        });
+    }
+}
+
+impl Visit for DependencyCollector {
+    fn visit_module_decl(&mut self, decl: &ModuleDecl) {
+        match decl {
+            ModuleDecl::Import(import) if !import.type_only => self.push(&import.src, DependencyKind::Static, import.with.as_deref()),
+            ModuleDecl::ExportAll(export) if !export.type_only => self.push(&export.src, DependencyKind::Static, export.with.as_deref()),
+            ModuleDecl::ExportNamed(export) if !export.type_only => if let Some(src) = &export.src {
+                self.push(src, DependencyKind::Static, export.with.as_deref());
+            },
+            _ => {}
+        }
+        decl.visit_children_with(self);
+    }
+
+    fn visit_call_expr(&mut self, call: &CallExpr) {
+        if let Some((specifier, attributes)) = get_dynamic_import(call) {
+            self.push(specifier, DependencyKind::Dynamic, attributes);
+        }
+        call.visit_children_with(self);
+    }
+}
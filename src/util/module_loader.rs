@@ -1,27 +1,326 @@
 use deno_runtime::deno_core;
 use deno_core::{
-  ModuleSpecifier, ModuleSource, ModuleType, RequestedModuleType, ResolutionKind,
-  resolve_import, ModuleSourceCode, ModuleLoader, ModuleLoadResponse
+  v8, FastString, ModuleSpecifier, ModuleSource, ModuleType, RequestedModuleType, ResolutionKind,
+  resolve_import, ModuleSourceCode, ModuleLoader, ModuleLoadResponse, SourceCodeCacheInfo, CustomModuleEvaluationKind
 };
+use deno_runtime::code_cache::{CodeCache, CodeCacheType};
 use deno_core::anyhow::Error;
 use deno_core::error::generic_error;
 use deno_runtime::deno_permissions::PermissionsContainer;
 use deno_core::futures::FutureExt;
+use deno_core::futures::Future;
+use deno_core::parking_lot::Mutex;
+use deno_core::serde_json;
 use import_map::ImportMap;
-use data_url::DataUrl;
+use deno_ast::MediaType;
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
 
 use mtsc::util::OptionSource;
 
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::path::PathBuf;
+use std::pin::Pin;
 use std::sync::Arc;
 
-use crate::util::{self, FileFetcher};
+use crate::util::{self, node, FileFetcher, File, SjsError, SourceMapStore, TranspileCache, SJSCodeCache, NpmResolver, NpmModuleFormat};
+use crate::util::cjs::wrap_commonjs;
+use crate::util::jsonc::{json5_to_json, strip_jsonc, JSON5_EXTENSION, JSON5_MIME_TYPE, JSONC_EXTENSIONS, JSONC_MIME_TYPES};
+use crate::util::code_cache::source_hash;
+use crate::util::includes::find_includes;
+use crate::util::watch::WatchedFiles;
 
+#[derive(Clone)]
 pub struct SJSModuleLoader {
   pub file_fetcher: Arc<FileFetcher>,
   pub macros: Vec<String>,
   pub include_paths: Vec<String>,
-  pub import_map: Option<ImportMap>,
+  pub import_map: Option<Arc<ImportMap>>,
+  /// Checked when loading the static module graph, which the main worker always loads in full
+  pub root_permissions: PermissionsContainer,
+  /// Checked when loading dynamic imports
+  pub permissions: PermissionsContainer,
+  /// Modules that were already compiled ahead of time, as those embedded in standalone binaries, by their final specifier
+  pub precompiled: Arc<HashSet<ModuleSpecifier>>,
+  /// Receives every local file loaded, including `#include`d files, when running in watch mode
+  pub watched_files: Option<WatchedFiles>,
+  /// Receives the source map of every module compiled by mtsc
+  pub source_maps: SourceMapStore,
+  /// Reuses mtsc output from earlier runs, `None` always compiles
+  pub transpile_cache: Option<TranspileCache>,
+  /// V8 code caches for JavaScript modules, shared between runs
+  pub code_cache: Option<Arc<SJSCodeCache>>,
+  /// Installs and resolves `npm:` packages, `None` leaves the scheme unsupported
+  pub npm_resolver: Option<Arc<NpmResolver>>,
+  /// Set when a web worker could not be set up, the next module load fails with it so the error reaches the parent
+  pub startup_error: Arc<Mutex<Option<SjsError>>>,
+}
+
+/// A fetched module after it has been compiled according to its type
+pub struct LoadedModule {
+  /// The fetched file, its specifier is the _final_ specifier after any redirects
+  pub file: File,
+  pub module_type: ModuleType,
+  /// The compiled source of JavaScript modules and the decoded text of JSON and text modules, other modules use
+  /// the file's bytes as is
+  pub code: Option<String>,
+  /// Maps `code` back to the original source when mtsc changed it
+  pub source_map: Option<Vec<u8>>,
+}
+
+impl LoadedModule {
+  pub fn into_module_source(self, module_specifier: &ModuleSpecifier, code_cache: Option<&SJSCodeCache>) -> ModuleSource {
+    // Redirected modules, including `npm:` specifiers, are named after the final specifier so their imports resolve against it
+    let found_specifier = &self.file.specifier;
+    let code = match (self.code, self.source_map) {
+      // Inline the source map as well so the inspector shows original sources
+      (Some(code), Some(source_map)) => format!("{}\n//# sourceMappingURL=data:application/json;base64,{}", code, BASE64_STANDARD.encode(source_map)),
+      (Some(code), None) => code,
+      (None, _) => return ModuleSource::new_with_redirect(self.module_type, ModuleSourceCode::Bytes(self.file.source.into()), module_specifier, found_specifier, None)
+    };
+
+    let code_cache = code_cache.filter(|_| self.module_type == ModuleType::JavaScript).map(|code_cache| {
+      let hash = source_hash(code.as_bytes());
+      SourceCodeCacheInfo {
+        hash,
+        data: code_cache.get_sync(found_specifier, CodeCacheType::EsModule, hash).map(Cow::Owned)
+      }
+    });
+
+    ModuleSource::new_with_redirect(
+      self.module_type,
+      ModuleSourceCode::String(code.into()),
+      module_specifier,
+      found_specifier,
+      code_cache
+    )
+  }
+}
+
+/// Evaluates the modules imported `with { type: "text" }` and `with { type: "bytes" }`, whose default export is
+/// their source as a string or a `Uint8Array`
+pub fn evaluate_raw_module(
+  scope: &mut v8::HandleScope,
+  module_type: Cow<'_, str>,
+  _module_name: &FastString,
+  code: ModuleSourceCode,
+) -> Result<CustomModuleEvaluationKind, Error> {
+  let value: v8::Local<v8::Value> = match (module_type.as_ref(), code) {
+    ("text", ModuleSourceCode::String(text)) => v8::String::new(scope, text.as_str()).ok_or_else(|| generic_error("Text module is too large"))?.into(),
+    ("bytes", ModuleSourceCode::Bytes(bytes)) => {
+      let backing_store = v8::ArrayBuffer::new_backing_store_from_vec(bytes.as_bytes().to_vec()).make_shared();
+      let buffer = v8::ArrayBuffer::with_backing_store(scope, &backing_store);
+      v8::Uint8Array::new(scope, buffer, 0, buffer.byte_length()).ok_or_else(|| generic_error("Bytes module is too large"))?.into()
+    },
+    (module_type, _) => return Err(generic_error(format!("Importing \"{}\" modules is not supported", module_type)))
+  };
+  Ok(CustomModuleEvaluationKind::Synthetic(v8::Global::new(scope, value)))
+}
+
+/// Chooses how mtsc compiles a fetched file and its module type, a `Content-Type` header takes precedence over
+/// the extension
+fn option_source(file: &File) -> OptionSource {
+  let content_type = file.maybe_headers.as_ref().and_then(|headers| headers.get("content-type"));
+  let essence = content_type.and_then(|content_type| content_type.split(';').next()).map(|essence| essence.trim().to_ascii_lowercase());
+  if let Some(essence) = essence.as_deref().filter(|essence| JSONC_MIME_TYPES.contains(essence)) {
+    return OptionSource::Mime(essence.to_string());
+  }
+  if content_type.is_some() {
+    let mime_type = match file.resolve_media_type_and_charset().0 {
+      MediaType::JavaScript | MediaType::Mjs | MediaType::Cjs => Some("text/javascript"),
+      MediaType::Jsx => Some("text/jsx"),
+      MediaType::TypeScript | MediaType::Mts | MediaType::Cts | MediaType::Dts | MediaType::Dmts | MediaType::Dcts => Some("application/typescript"),
+      MediaType::Tsx => Some("text/tsx"),
+      MediaType::Json => Some("application/json"),
+      MediaType::Wasm => Some("application/wasm"),
+      _ => None
+    };
+    if let Some(mime_type) = mime_type {
+      return OptionSource::Mime(mime_type.to_string());
+    }
+  }
+
+  match file.specifier.scheme() {
+    "file" | "http" | "https" => OptionSource::Path(PathBuf::from(file.specifier.path().to_string())),
+    // Types deno doesn't know are passed to mtsc as is, blobs created without a type are compiled like `sjs:` modules
+    "data" | "blob" => match essence {
+      Some(mime_type) if !mime_type.is_empty() => OptionSource::Mime(mime_type),
+      _ => OptionSource::None
+    },
+    "sjs" | _ => OptionSource::None
+  }
+}
+
+impl SJSModuleLoader {
+  /// Fetches a module and runs it through mtsc, this is shared by the runtime and tools that walk the module graph
+  pub async fn load_module(
+    &self,
+    module_specifier: &ModuleSpecifier,
+    requested_module_type: RequestedModuleType,
+    is_dynamic: bool,
+  ) -> Result<LoadedModule, Error> {
+    if module_specifier.scheme() == "node" {
+      let source = node::builtin_source(module_specifier).ok_or_else(|| generic_error(format!("Unknown built-in module \"{}\"", module_specifier)))?;
+      return Ok(LoadedModule {
+        file: File {
+          specifier: module_specifier.clone(),
+          maybe_headers: None,
+          source: source.as_bytes().into()
+        },
+        module_type: ModuleType::JavaScript,
+        code: Some(source.to_string()),
+        source_map: None
+      });
+    }
+
+    // `npm:` specifiers act as redirects to a file in the installed package
+    let module_specifier = &match (module_specifier.scheme(), &self.npm_resolver) {
+      ("npm", Some(npm_resolver)) => npm_resolver.resolve_npm_specifier(module_specifier).await.map_err(|error| SjsError::Fetch { specifier: module_specifier.clone(), error })?,
+      _ => module_specifier.clone()
+    };
+    let npm_module_format = self.npm_resolver.as_ref().and_then(|npm_resolver| npm_resolver.module_format(module_specifier));
+
+    let local_path = module_specifier.to_file_path().ok().filter(|_| module_specifier.scheme() == "file");
+    if let (Some(watched_files), Some(path)) = (&self.watched_files, &local_path) {
+      watched_files.add(path.clone());
+    }
+
+    let permissions = if is_dynamic { &self.permissions } else { &self.root_permissions };
+    let file = self.file_fetcher.fetch(module_specifier,permissions.clone()).await.map_err(|error| SjsError::Fetch { specifier: module_specifier.clone(), error })?;
+
+    let opt_source = option_source(&file);
+
+    let mut mtsc_options = mtsc::Options {
+      module: true,
+      preprocess: false,
+      transpile: false,
+      filename: Some(module_specifier.clone().into()),
+      macros: self.macros.clone(),
+      include_paths: self.include_paths.clone(),
+      ..Default::default()
+    };
+
+    mtsc::util::update_options(opt_source.clone(), &mut mtsc_options, &mtsc::util::all_options());
+
+    if opt_source == OptionSource::None {
+      mtsc_options.preprocess = true;
+      mtsc_options.transpile = true;
+    }
+
+    let module_type = match &requested_module_type {
+      // The contents of text and bytes modules are never interpreted, whatever their type
+      RequestedModuleType::Other(ty) if matches!(ty.as_ref(), "text" | "bytes") => Some(ModuleType::Other(ty.clone())),
+      _ => None
+    }.or_else(|| match &opt_source {
+      OptionSource::Mime(mime_type) => match mime_type.as_str() {
+        "application/json" => Some(ModuleType::Json),
+        mime_type if JSONC_MIME_TYPES.contains(&mime_type) => Some(ModuleType::Json),
+        "application/wasm" => Some(ModuleType::Wasm),
+        _ => None
+      },
+      OptionSource::Path(path) => path.extension().and_then(|ext| ext.to_str()).and_then(|ext| match ext {
+        "json" => Some(ModuleType::Json),
+        ext if JSONC_EXTENSIONS.contains(&ext) => Some(ModuleType::Json),
+        "wasm" => Some(ModuleType::Wasm),
+        _ => None
+      }),
+      _ => None
+    }).unwrap_or_else(|| match &requested_module_type {
+      RequestedModuleType::Other(ty) => ModuleType::Other(ty.clone()),
+      _ => ModuleType::JavaScript,
+    });
+
+    if module_type == ModuleType::Json && requested_module_type != RequestedModuleType::Json {
+      return Err(generic_error("Attempted to load JSON module without specifying \"type\": \"json\" attribute in the import statement"));
+    }
+
+    let precompiled = module_type == ModuleType::JavaScript && self.precompiled.contains(&file.specifier);
+    // Sources are decoded with the charset of their `Content-Type` or, for local files, their BOM, precompiled code
+    // was written as UTF-8 whatever the original charset was
+    let text: Option<Arc<str>> = match module_type {
+      _ if precompiled => Some(std::str::from_utf8(&file.source)?.into()),
+      ModuleType::JavaScript | ModuleType::Json => Some(file.clone().into_text_decoded()?.source),
+      ModuleType::Other(ref ty) if *ty == "text" => Some(file.clone().into_text_decoded()?.source),
+      _ => None
+    };
+
+    if let (Some(watched_files), Some(text)) = (self.watched_files.as_ref().filter(|_| module_type == ModuleType::JavaScript && mtsc_options.preprocess), &text) {
+      let dir = local_path.as_deref().and_then(|path| path.parent());
+      for path in find_includes(text, dir, &self.include_paths) {
+        watched_files.add(path);
+      }
+    }
+
+    // `Some(true)` for JSON5 and `Some(false)` for JSONC modules
+    let relaxed_json = match &opt_source {
+      OptionSource::Mime(mime_type) if JSONC_MIME_TYPES.contains(&mime_type.as_str()) => Some(mime_type == JSON5_MIME_TYPE),
+      OptionSource::Path(path) => path.extension().and_then(|ext| ext.to_str()).filter(|ext| JSONC_EXTENSIONS.contains(ext)).map(|ext| ext == JSON5_EXTENSION),
+      _ => None
+    }.filter(|_| module_type == ModuleType::Json);
+
+    let (code, source_map) = if let (Some(is_json5), Some(text)) = (relaxed_json, text.as_deref()) {
+      let json = if is_json5 {
+        json5_to_json(text).map_err(|x| generic_error(format!("{}: {}", module_specifier, x)))?
+      } else {
+        let json = strip_jsonc(text).into_owned();
+        // V8's message would not say why JSON5 syntax is rejected
+        serde_json::from_str::<serde::de::IgnoredAny>(&json).map_err(|x| generic_error(format!(
+          "{}: {} (JSONC only allows comments and trailing commas, unquoted keys, single quoted strings and other JSON5 syntax need a .json5 file)", module_specifier, x
+        )))?;
+        json
+      };
+      (Some(json), None)
+    } else if let Some(text) = text.as_deref().filter(|_| precompiled || module_type != ModuleType::JavaScript) {
+      (Some(text.to_string()), None)
+    } else if let (Some(format), Some(source)) = (npm_module_format.filter(|_| module_type == ModuleType::JavaScript), text.as_deref()) {
+      // Packages are published as plain JavaScript, so they skip mtsc
+      let wrapped = match format {
+        NpmModuleFormat::Esm => None,
+        NpmModuleFormat::CommonJs => wrap_commonjs(&file.specifier, source, |specifier| self.resolve(specifier, file.specifier.as_str(), ResolutionKind::Import).is_ok())
+      };
+      (Some(wrapped.unwrap_or_else(|| source.to_string())), None)
+    } else if let Some(original) = text.as_deref() {
+      let cache_key = TranspileCache::key(module_specifier, original, &opt_source, &mtsc_options);
+
+      let (code, source_map) = match self.transpile_cache.as_ref().and_then(|cache| cache.get(&cache_key)) {
+        Some(cached) => cached,
+        None => {
+          let code = mtsc::compile(original,&mtsc_options).ok_or_else(|| SjsError::Compile { specifier: module_specifier.clone() })?;
+          let source_map = if mtsc_options.preprocess || mtsc_options.transpile {
+            let include_paths = Some(self.include_paths.as_slice()).filter(|_| mtsc_options.preprocess);
+            Some(util::create_source_map(&file.specifier, original, &code, include_paths)?)
+          } else {
+            None
+          };
+
+          if let Some(cache) = &self.transpile_cache {
+            let includes = if mtsc_options.preprocess {
+              find_includes(original, local_path.as_deref().and_then(|path| path.parent()), &self.include_paths)
+            } else {
+              vec![]
+            };
+            cache.set(&cache_key, &code, source_map.as_deref(), includes);
+          }
+          (code, source_map)
+        }
+      };
+
+      if let Some(source_map) = &source_map {
+        self.source_maps.insert(&file.specifier, source_map);
+      }
+      (Some(code), source_map)
+    } else {
+      (None, None)
+    };
+
+    Ok(LoadedModule {
+      file,
+      module_type,
+      code,
+      source_map
+    })
+  }
 }
 
 impl ModuleLoader for SJSModuleLoader {
@@ -31,6 +330,12 @@ impl ModuleLoader for SJSModuleLoader {
       referrer: &str,
       _kind: ResolutionKind,
     ) -> Result<ModuleSpecifier, Error> {
+      if let Some(npm_resolver) = &self.npm_resolver {
+        if let Some(resolved) = npm_resolver.resolve_in_package(specifier, &util::url::resolve_maybe_url(referrer)?)? {
+          return Ok(resolved);
+        }
+      }
+
       return match &self.import_map {
         Some(import_map) => Ok(import_map.resolve(specifier, &util::url::resolve_maybe_url(referrer)?)?),
         None => Ok(resolve_import(specifier, referrer)?)
@@ -41,76 +346,32 @@ impl ModuleLoader for SJSModuleLoader {
       &self,
       module_specifier: &ModuleSpecifier,
       _maybe_referrer: Option<&ModuleSpecifier>,
-      _is_dynamic: bool,
+      is_dynamic: bool,
       requested_module_type: RequestedModuleType,
     ) -> ModuleLoadResponse {
       let module_specifier = module_specifier.clone();
-      let file_fetcher = self.file_fetcher.clone();
-
-      let macros = self.macros.clone();
-      let include_paths = self.include_paths.clone();
+      let loader = self.clone();
 
       return ModuleLoadResponse::Async(
         async move {
-          let opt_source = match module_specifier.scheme() {
-            "data" => OptionSource::Mime(DataUrl::process(module_specifier.as_str()).map_err(|_| generic_error("URL has scheme \"data\" but is not a valid Data Url"))?.mime_type().to_string()),
-            "file" | "http" | "https" => OptionSource::Path(PathBuf::from(module_specifier.path().to_string())),
-            "sjs" | "blob" | _ => OptionSource::None
-          };
-
-          let mut mtsc_options = mtsc::Options {
-            module: true,
-            preprocess: false,
-            transpile: false,
-            filename: Some(module_specifier.clone().into()),
-            macros,
-            include_paths,
-            ..Default::default()
-          };
-
-          mtsc::util::update_options(opt_source.clone(), &mut mtsc_options, &mtsc::util::all_options());
-
-          if opt_source == OptionSource::None {
-            mtsc_options.preprocess = true;
-            mtsc_options.transpile = true;
-          }
-
-          let module_type = match &opt_source {
-            OptionSource::Mime(mime_type) => match mime_type.as_str() {
-              "application/json" => Some(ModuleType::Json),
-              "application/wasm" => Some(ModuleType::Wasm),
-              _ => None
-            },
-            OptionSource::Path(path) => path.extension().and_then(|ext| ext.to_str()).and_then(|ext| match ext {
-              "json" => Some(ModuleType::Json),
-              "wasm" => Some(ModuleType::Wasm),
-              _ => None
-            }),
-            _ => None
-          }.unwrap_or_else(|| match &requested_module_type {
-            RequestedModuleType::Other(ty) => ModuleType::Other(ty.clone()),
-            _ => ModuleType::JavaScript,
-          });
-
-          if module_type == ModuleType::Json && requested_module_type != RequestedModuleType::Json {
-            return Err(generic_error("Attempted to load JSON module without specifying \"type\": \"json\" attribute in the import statement"));
+          if let Some(err) = loader.startup_error.lock().take() {
+            return Err(err.into());
           }
-
-          let code = file_fetcher.fetch(&module_specifier,PermissionsContainer::allow_all()).await.map_err(|x| generic_error(format!("{}: {}",module_specifier,x)))?.source.clone();
-          
-          let code = if module_type == ModuleType::JavaScript {
-            ModuleSourceCode::String(mtsc::compile(std::str::from_utf8(&code)?,&mtsc_options).ok_or_else(|| generic_error("Failed to compile script"))?.into())
-          } else {
-            ModuleSourceCode::Bytes(code.into())
-          };
-
-          return Ok(ModuleSource::new(
-            module_type,
-            code,
-            &module_specifier,
-            None // TODO implement source code cache?
-          ))
+          let module = loader.load_module(&module_specifier, requested_module_type, is_dynamic).await?;
+          return Ok(module.into_module_source(&module_specifier, loader.code_cache.as_deref()));
         }.boxed_local()
       )
     }
+
+    fn code_cache_ready(
+      &self,
+      module_specifier: ModuleSpecifier,
+      hash: u64,
+      code_cache: &[u8],
+    ) -> Pin<Box<dyn Future<Output = ()>>> {
+      if let Some(cache) = &self.code_cache {
+        cache.set_sync(module_specifier, CodeCacheType::EsModule, hash, code_cache);
+      }
+      async {}.boxed_local()
+    }
   }
\ No newline at end of file
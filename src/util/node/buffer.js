@@ -0,0 +1,429 @@
+// node:buffer
+
+const encoder = new TextEncoder();
+const utf8Decoder = new TextDecoder();
+
+function normalizeEncoding(encoding = "utf8") {
+  switch (String(encoding).toLowerCase()) {
+    case "utf8":
+    case "utf-8":
+      return "utf8";
+    case "hex":
+      return "hex";
+    case "base64":
+      return "base64";
+    case "base64url":
+      return "base64url";
+    case "latin1":
+    case "binary":
+      return "latin1";
+    case "ascii":
+      return "ascii";
+    case "ucs2":
+    case "ucs-2":
+    case "utf16le":
+    case "utf-16le":
+      return "utf16le";
+    default:
+      return undefined;
+  }
+}
+
+function checkEncoding(encoding) {
+  const normalized = normalizeEncoding(encoding);
+  if (normalized === undefined) {
+    throw new TypeError(`Unknown encoding: ${encoding}`);
+  }
+  return normalized;
+}
+
+function encode(string, encoding) {
+  switch (checkEncoding(encoding)) {
+    case "utf8":
+      return encoder.encode(string);
+    case "hex": {
+      const bytes = new Uint8Array(Math.floor(string.length / 2));
+      for (let i = 0; i < bytes.length; i++) {
+        const byte = parseInt(string.substr(i * 2, 2), 16);
+        if (Number.isNaN(byte)) {
+          return bytes.subarray(0, i);
+        }
+        bytes[i] = byte;
+      }
+      return bytes;
+    }
+    case "base64":
+    case "base64url": {
+      const base64 = string.replace(/[^A-Za-z0-9+/\-_]/g, "").replace(/-/g, "+").replace(/_/g, "/");
+      const binary = atob(base64.padEnd(Math.ceil(base64.length / 4) * 4, "="));
+      return Uint8Array.from(binary, (c) => c.charCodeAt(0));
+    }
+    case "latin1":
+    case "ascii":
+      return Uint8Array.from(string, (c) => c.charCodeAt(0) & 0xff);
+    case "utf16le": {
+      const bytes = new Uint8Array(string.length * 2);
+      for (let i = 0; i < string.length; i++) {
+        const code = string.charCodeAt(i);
+        bytes[i * 2] = code & 0xff;
+        bytes[i * 2 + 1] = code >> 8;
+      }
+      return bytes;
+    }
+  }
+}
+
+function decode(bytes, encoding) {
+  const normalized = checkEncoding(encoding);
+  switch (normalized) {
+    case "utf8":
+      return utf8Decoder.decode(bytes);
+    case "hex":
+      return Array.from(bytes, (byte) => byte.toString(16).padStart(2, "0")).join("");
+    case "base64":
+    case "base64url": {
+      let binary = "";
+      for (let i = 0; i < bytes.length; i += 0x8000) {
+        binary += String.fromCharCode(...bytes.subarray(i, i + 0x8000));
+      }
+      const base64 = btoa(binary);
+      return normalized === "base64" ? base64 : base64.replace(/\+/g, "-").replace(/\//g, "_").replace(/=+$/, "");
+    }
+    case "latin1":
+      return Array.from(bytes, (byte) => String.fromCharCode(byte)).join("");
+    case "ascii":
+      return Array.from(bytes, (byte) => String.fromCharCode(byte & 0x7f)).join("");
+    case "utf16le": {
+      let string = "";
+      for (let i = 0; i + 1 < bytes.length; i += 2) {
+        string += String.fromCharCode(bytes[i] | (bytes[i + 1] << 8));
+      }
+      return string;
+    }
+  }
+}
+
+export class Buffer extends Uint8Array {
+  static poolSize = 8192;
+
+  static from(value, encodingOrOffset, length) {
+    if (typeof value === "string") {
+      const bytes = encode(value, encodingOrOffset);
+      return new Buffer(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+    }
+    if (value instanceof ArrayBuffer || value instanceof SharedArrayBuffer) {
+      const offset = encodingOrOffset ?? 0;
+      return new Buffer(value, offset, length ?? value.byteLength - offset);
+    }
+    if (ArrayBuffer.isView(value)) {
+      return Buffer.copyBytesFrom(value);
+    }
+    if (value?.type === "Buffer" && Array.isArray(value.data)) {
+      return Buffer.from(value.data);
+    }
+    if (value != null && typeof value[Symbol.toPrimitive] === "function") {
+      return Buffer.from(value[Symbol.toPrimitive]("string"), encodingOrOffset);
+    }
+    if (value != null && typeof value.length === "number") {
+      const buffer = Buffer.alloc(value.length);
+      for (let i = 0; i < value.length; i++) {
+        buffer[i] = value[i];
+      }
+      return buffer;
+    }
+    throw new TypeError("The first argument must be of type string, Buffer, ArrayBuffer, Array, or Array-like Object");
+  }
+
+  static copyBytesFrom(view, offset = 0, length) {
+    const bytes = new Uint8Array(view.buffer, view.byteOffset, view.byteLength);
+    const start = offset * (view.BYTES_PER_ELEMENT ?? 1);
+    const end = length === undefined ? bytes.length : start + length * (view.BYTES_PER_ELEMENT ?? 1);
+    const buffer = Buffer.alloc(Math.max(0, Math.min(end, bytes.length) - start));
+    buffer.set(bytes.subarray(start, start + buffer.length));
+    return buffer;
+  }
+
+  static alloc(size, fill, encoding) {
+    const buffer = new Buffer(size);
+    if (fill !== undefined && fill !== 0) {
+      buffer.fill(fill, 0, size, encoding);
+    }
+    return buffer;
+  }
+
+  static allocUnsafe(size) {
+    return new Buffer(size);
+  }
+
+  static allocUnsafeSlow(size) {
+    return new Buffer(size);
+  }
+
+  static isBuffer(value) {
+    return value instanceof Buffer;
+  }
+
+  static isEncoding(encoding) {
+    return typeof encoding === "string" && normalizeEncoding(encoding) !== undefined;
+  }
+
+  static byteLength(value, encoding) {
+    if (typeof value !== "string") {
+      return value.byteLength;
+    }
+    return encode(value, encoding).byteLength;
+  }
+
+  static concat(list, totalLength) {
+    totalLength ??= list.reduce((total, bytes) => total + bytes.length, 0);
+    const buffer = Buffer.alloc(totalLength);
+    let offset = 0;
+    for (const bytes of list) {
+      if (offset >= totalLength) {
+        break;
+      }
+      buffer.set(bytes.subarray(0, totalLength - offset), offset);
+      offset += bytes.length;
+    }
+    return buffer;
+  }
+
+  static compare(a, b) {
+    return a.compare(b);
+  }
+
+  toString(encoding = "utf8", start = 0, end = this.length) {
+    return decode(this.subarray(start, end), encoding);
+  }
+
+  toJSON() {
+    return { type: "Buffer", data: Array.from(this) };
+  }
+
+  equals(other) {
+    return this.compare(other) === 0;
+  }
+
+  compare(target, targetStart = 0, targetEnd = target.length, sourceStart = 0, sourceEnd = this.length) {
+    const a = this.subarray(sourceStart, sourceEnd);
+    const b = target.subarray(targetStart, targetEnd);
+    for (let i = 0; i < Math.min(a.length, b.length); i++) {
+      if (a[i] !== b[i]) {
+        return a[i] < b[i] ? -1 : 1;
+      }
+    }
+    return Math.sign(a.length - b.length);
+  }
+
+  copy(target, targetStart = 0, sourceStart = 0, sourceEnd = this.length) {
+    const bytes = this.subarray(sourceStart, Math.min(sourceEnd, sourceStart + target.length - targetStart));
+    target.set(bytes, targetStart);
+    return bytes.length;
+  }
+
+  write(string, offset = 0, length = this.length - offset, encoding = "utf8") {
+    if (typeof offset === "string") {
+      [offset, length, encoding] = [0, this.length, offset];
+    } else if (typeof length === "string") {
+      [length, encoding] = [this.length - offset, length];
+    }
+    const bytes = encode(string, encoding).subarray(0, Math.min(length, this.length - offset));
+    this.set(bytes, offset);
+    return bytes.length;
+  }
+
+  fill(value, offset = 0, end = this.length, encoding) {
+    if (typeof offset === "string") {
+      [offset, end, encoding] = [0, this.length, offset];
+    }
+    if (typeof value === "number") {
+      return super.fill(value & 0xff, offset, end);
+    }
+    const bytes = typeof value === "string" ? encode(value, encoding) : value;
+    if (bytes.length === 0) {
+      return super.fill(0, offset, end);
+    }
+    for (let i = offset; i < end; i++) {
+      this[i] = bytes[(i - offset) % bytes.length];
+    }
+    return this;
+  }
+
+  indexOf(value, byteOffset = 0, encoding) {
+    if (typeof value === "number") {
+      return super.indexOf(value & 0xff, byteOffset);
+    }
+    const needle = typeof value === "string" ? encode(value, encoding) : value;
+    const start = byteOffset < 0 ? Math.max(0, this.length + byteOffset) : byteOffset;
+    outer: for (let i = start; i <= this.length - needle.length; i++) {
+      for (let j = 0; j < needle.length; j++) {
+        if (this[i + j] !== needle[j]) {
+          continue outer;
+        }
+      }
+      return i;
+    }
+    return -1;
+  }
+
+  includes(value, byteOffset, encoding) {
+    return this.indexOf(value, byteOffset, encoding) !== -1;
+  }
+
+  slice(start, end) {
+    return this.subarray(start, end);
+  }
+
+  subarray(start, end) {
+    const bytes = Uint8Array.prototype.subarray.call(this, start, end);
+    return new Buffer(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+  }
+
+  #view() {
+    return new DataView(this.buffer, this.byteOffset, this.byteLength);
+  }
+
+  readUInt8(offset = 0) {
+    return this.#view().getUint8(offset);
+  }
+
+  readUInt16LE(offset = 0) {
+    return this.#view().getUint16(offset, true);
+  }
+
+  readUInt16BE(offset = 0) {
+    return this.#view().getUint16(offset, false);
+  }
+
+  readUInt32LE(offset = 0) {
+    return this.#view().getUint32(offset, true);
+  }
+
+  readUInt32BE(offset = 0) {
+    return this.#view().getUint32(offset, false);
+  }
+
+  readInt8(offset = 0) {
+    return this.#view().getInt8(offset);
+  }
+
+  readInt16LE(offset = 0) {
+    return this.#view().getInt16(offset, true);
+  }
+
+  readInt16BE(offset = 0) {
+    return this.#view().getInt16(offset, false);
+  }
+
+  readInt32LE(offset = 0) {
+    return this.#view().getInt32(offset, true);
+  }
+
+  readInt32BE(offset = 0) {
+    return this.#view().getInt32(offset, false);
+  }
+
+  readBigUInt64LE(offset = 0) {
+    return this.#view().getBigUint64(offset, true);
+  }
+
+  readBigUInt64BE(offset = 0) {
+    return this.#view().getBigUint64(offset, false);
+  }
+
+  readFloatLE(offset = 0) {
+    return this.#view().getFloat32(offset, true);
+  }
+
+  readFloatBE(offset = 0) {
+    return this.#view().getFloat32(offset, false);
+  }
+
+  readDoubleLE(offset = 0) {
+    return this.#view().getFloat64(offset, true);
+  }
+
+  readDoubleBE(offset = 0) {
+    return this.#view().getFloat64(offset, false);
+  }
+
+  writeUInt8(value, offset = 0) {
+    this.#view().setUint8(offset, value);
+    return offset + 1;
+  }
+
+  writeUInt16LE(value, offset = 0) {
+    this.#view().setUint16(offset, value, true);
+    return offset + 2;
+  }
+
+  writeUInt16BE(value, offset = 0) {
+    this.#view().setUint16(offset, value, false);
+    return offset + 2;
+  }
+
+  writeUInt32LE(value, offset = 0) {
+    this.#view().setUint32(offset, value, true);
+    return offset + 4;
+  }
+
+  writeUInt32BE(value, offset = 0) {
+    this.#view().setUint32(offset, value, false);
+    return offset + 4;
+  }
+
+  writeInt8(value, offset = 0) {
+    this.#view().setInt8(offset, value);
+    return offset + 1;
+  }
+
+  writeInt16LE(value, offset = 0) {
+    this.#view().setInt16(offset, value, true);
+    return offset + 2;
+  }
+
+  writeInt16BE(value, offset = 0) {
+    this.#view().setInt16(offset, value, false);
+    return offset + 2;
+  }
+
+  writeInt32LE(value, offset = 0) {
+    this.#view().setInt32(offset, value, true);
+    return offset + 4;
+  }
+
+  writeInt32BE(value, offset = 0) {
+    this.#view().setInt32(offset, value, false);
+    return offset + 4;
+  }
+
+  writeDoubleLE(value, offset = 0) {
+    this.#view().setFloat64(offset, value, true);
+    return offset + 8;
+  }
+
+  writeDoubleBE(value, offset = 0) {
+    this.#view().setFloat64(offset, value, false);
+    return offset + 8;
+  }
+}
+
+// Node accepts both spellings of the unsigned methods
+for (const name of Object.getOwnPropertyNames(Buffer.prototype)) {
+  if (name.includes("UInt")) {
+    Buffer.prototype[name.replace("UInt", "Uint")] = Buffer.prototype[name];
+  }
+}
+
+export const kMaxLength = 2 ** 32 - 1;
+export const constants = { MAX_LENGTH: kMaxLength, MAX_STRING_LENGTH: 2 ** 29 - 24 };
+
+export const atob = globalThis.atob;
+export const btoa = globalThis.btoa;
+export const Blob = globalThis.Blob;
+export const File = globalThis.File;
+
+const buffer = { Buffer, kMaxLength, constants, atob, btoa, Blob, File };
+
+export { buffer as "module.exports" };
+export default buffer;
@@ -0,0 +1,286 @@
+// node:child_process
+
+import { EventEmitter } from "node:events";
+import { Buffer } from "node:buffer";
+
+const isWindows = system.build.os === "windows";
+
+// Commands given as a single string run through the platform shell, as they do in Node
+function shellCommand(command) {
+  return isWindows ? ["cmd.exe", ["/d", "/s", "/c", command]] : ["/bin/sh", ["-c", command]];
+}
+
+function commandOptions(options, stdio) {
+  return {
+    cwd: options?.cwd instanceof URL ? options.cwd.pathname : options?.cwd,
+    env: options?.env,
+    clearEnv: options?.env !== undefined,
+    uid: options?.uid,
+    gid: options?.gid,
+    windowsRawArguments: options?.windowsVerbatimArguments,
+    ...stdio,
+  };
+}
+
+function toStdio(value) {
+  switch (value) {
+    case "inherit":
+      return "inherit";
+    case "ignore":
+      return "null";
+    default:
+      return "piped";
+  }
+}
+
+function stdioOptions(stdio = "pipe") {
+  const [stdin, stdout, stderr] = typeof stdio === "string" ? [stdio, stdio, stdio] : stdio;
+  return {
+    stdin: toStdio(stdin),
+    stdout: toStdio(stdout),
+    stderr: toStdio(stderr),
+  };
+}
+
+function decode(bytes, encoding) {
+  const buffer = Buffer.from(bytes ?? []);
+  return encoding && encoding !== "buffer" ? buffer.toString(encoding) : buffer;
+}
+
+export function spawnSync(file, args, options) {
+  if (!Array.isArray(args)) {
+    options = args;
+    args = [];
+  }
+  const [command, commandArgs] = options?.shell ? shellCommand([file, ...args].join(" ")) : [file, args];
+  const stdio = stdioOptions(options?.stdio);
+  if (options?.input !== undefined) {
+    // `outputSync` has no way to write to the child's stdin
+    throw new TypeError("The \"input\" option of synchronous child processes is not supported");
+  }
+
+  try {
+    const output = new system.Command(command, { args: commandArgs, ...commandOptions(options, { ...stdio, stdin: stdio.stdin === "piped" ? "null" : stdio.stdin }) }).outputSync();
+    const stdout = stdio.stdout === "piped" ? decode(output.stdout, options?.encoding) : null;
+    const stderr = stdio.stderr === "piped" ? decode(output.stderr, options?.encoding) : null;
+    return {
+      pid: 0,
+      output: [null, stdout, stderr],
+      stdout,
+      stderr,
+      status: output.signal ? null : output.code,
+      signal: output.signal,
+    };
+  } catch (error) {
+    return {
+      pid: 0,
+      output: null,
+      stdout: null,
+      stderr: null,
+      status: null,
+      signal: null,
+      error: Object.assign(error, { code: error.name === "NotFound" ? "ENOENT" : error.code, path: file, spawnargs: args }),
+    };
+  }
+}
+
+function checkResult(result, command) {
+  if (result.error) {
+    throw result.error;
+  }
+  if (result.status !== 0) {
+    const error = new Error(`Command failed: ${command}${result.stderr?.length ? `\n${result.stderr}` : ""}`);
+    throw Object.assign(error, result);
+  }
+}
+
+export function execFileSync(file, args, options) {
+  if (!Array.isArray(args)) {
+    options = args;
+    args = [];
+  }
+  const result = spawnSync(file, args, { stdio: ["pipe", "pipe", "inherit"], ...options });
+  checkResult(result, [file, ...args].join(" "));
+  return result.stdout;
+}
+
+export function execSync(command, options) {
+  const result = spawnSync(command, [], { stdio: ["pipe", "pipe", "inherit"], ...options, shell: true });
+  checkResult(result, command);
+  return result.stdout;
+}
+
+// Pipes a child's `ReadableStream` into a stream-like emitter of "data" events
+function createReadable(stream, encoding) {
+  if (!stream) {
+    return null;
+  }
+  const readable = new EventEmitter();
+  readable.setEncoding = (value) => {
+    encoding = value;
+    return readable;
+  };
+  readable.done = (async () => {
+    // Wait a turn so listeners attached right after spawn see every chunk
+    await null;
+    for await (const chunk of stream) {
+      readable.emit("data", decode(chunk, encoding));
+    }
+    readable.emit("end");
+    readable.emit("close");
+  })();
+  return readable;
+}
+
+function createWritable(stream) {
+  if (!stream) {
+    return null;
+  }
+  const writer = stream.getWriter();
+  const writable = new EventEmitter();
+  return Object.assign(writable, {
+    write(chunk, encoding, callback) {
+      callback = typeof encoding === "function" ? encoding : callback;
+      const bytes = typeof chunk === "string" ? Buffer.from(chunk, typeof encoding === "string" ? encoding : "utf8") : chunk;
+      writer.write(bytes).then(() => callback?.(), (error) => callback ? callback(error) : writable.emit("error", error));
+      return true;
+    },
+    end(chunk, encoding, callback) {
+      if (chunk !== undefined && typeof chunk !== "function") {
+        writable.write(chunk, encoding);
+      }
+      callback = [chunk, encoding, callback].find((arg) => typeof arg === "function");
+      writer.close().then(() => {
+        writable.emit("finish");
+        callback?.();
+      }, () => {});
+      return writable;
+    },
+  });
+}
+
+export class ChildProcess extends EventEmitter {
+  constructor() {
+    super();
+    this.pid = undefined;
+    this.exitCode = null;
+    this.signalCode = null;
+    this.killed = false;
+    this.stdin = null;
+    this.stdout = null;
+    this.stderr = null;
+  }
+
+  kill(signal = "SIGTERM") {
+    try {
+      this.child?.kill(typeof signal === "number" ? "SIGTERM" : signal);
+      this.killed = true;
+    } catch {
+      return false;
+    }
+    return true;
+  }
+
+  ref() {
+    this.child?.ref();
+  }
+
+  unref() {
+    this.child?.unref();
+  }
+}
+
+export function spawn(file, args, options) {
+  if (!Array.isArray(args)) {
+    options = args;
+    args = [];
+  }
+  const [command, commandArgs] = options?.shell ? shellCommand([file, ...args].join(" ")) : [file, args];
+  const process = new ChildProcess();
+  process.spawnfile = command;
+  process.spawnargs = [command, ...commandArgs];
+
+  let child;
+  try {
+    child = new system.Command(command, { args: commandArgs, ...commandOptions(options, stdioOptions(options?.stdio)) }).spawn();
+  } catch (error) {
+    queueMicrotask(() => {
+      process.emit("error", Object.assign(error, { code: error.name === "NotFound" ? "ENOENT" : error.code, path: file, spawnargs: args }));
+      process.emit("close", -2, null);
+    });
+    return process;
+  }
+
+  Object.defineProperty(process, "child", { value: child, enumerable: false });
+  process.pid = child.pid;
+  process.stdin = createWritable(tryGet(() => child.stdin));
+  process.stdout = createReadable(tryGet(() => child.stdout), options?.encoding);
+  process.stderr = createReadable(tryGet(() => child.stderr), options?.encoding);
+  process.stdio = [process.stdin, process.stdout, process.stderr];
+
+  queueMicrotask(() => process.emit("spawn"));
+  child.status.then(async (status) => {
+    process.exitCode = status.signal ? null : status.code;
+    process.signalCode = status.signal;
+    process.emit("exit", process.exitCode, process.signalCode);
+    await Promise.all([process.stdout?.done, process.stderr?.done]);
+    process.emit("close", process.exitCode, process.signalCode);
+  });
+  return process;
+}
+
+// Accessing the pipe of a stream that is not "piped" throws
+function tryGet(fn) {
+  try {
+    return fn();
+  } catch {
+    return null;
+  }
+}
+
+export function execFile(file, args, options, callback) {
+  if (typeof args === "function") {
+    [args, options, callback] = [[], undefined, args];
+  } else if (!Array.isArray(args)) {
+    [args, options, callback] = [[], args, options];
+  }
+  if (typeof options === "function") {
+    [options, callback] = [undefined, options];
+  }
+
+  const child = spawn(file, args, { ...options, stdio: "pipe" });
+  const stdout = [];
+  const stderr = [];
+  child.stdout?.on("data", (chunk) => stdout.push(Buffer.from(chunk)));
+  child.stderr?.on("data", (chunk) => stderr.push(Buffer.from(chunk)));
+  child.stdin?.end();
+
+  let failed = false;
+  child.on("error", (error) => {
+    failed = true;
+    callback?.(error, "", "");
+  });
+  child.on("close", (code, signal) => {
+    if (failed) {
+      return;
+    }
+    const encoding = options?.encoding ?? "utf8";
+    const out = decode(Buffer.concat(stdout), encoding);
+    const err = decode(Buffer.concat(stderr), encoding);
+    const error = code === 0 ? null : Object.assign(new Error(`Command failed: ${[file, ...args].join(" ")}\n${err}`), { code, signal, killed: child.killed });
+    callback?.(error, out, err);
+  });
+  return child;
+}
+
+export function exec(command, options, callback) {
+  if (typeof options === "function") {
+    [options, callback] = [undefined, options];
+  }
+  return execFile(command, [], { ...options, shell: true }, callback);
+}
+
+const child_process = { ChildProcess, spawn, spawnSync, exec, execSync, execFile, execFileSync };
+
+export { child_process as "module.exports" };
+export default child_process;
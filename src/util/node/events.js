@@ -0,0 +1,180 @@
+// node:events
+
+const kListeners = Symbol("listeners");
+const kMaxListeners = Symbol("maxListeners");
+
+function getListeners(emitter) {
+  if (!Object.hasOwn(emitter, kListeners)) {
+    Object.defineProperty(emitter, kListeners, { value: new Map(), enumerable: false });
+  }
+  return emitter[kListeners];
+}
+
+function checkListener(listener) {
+  if (typeof listener !== "function") {
+    throw new TypeError(`The "listener" argument must be of type function. Received type ${typeof listener}`);
+  }
+}
+
+// A plain constructor rather than a class so older code can call `EventEmitter.call(this)` when inheriting
+export function EventEmitter() {
+  getListeners(this);
+}
+
+EventEmitter.defaultMaxListeners = 10;
+EventEmitter.captureRejections = false;
+EventEmitter.errorMonitor = Symbol("events.errorMonitor");
+
+function addListener(emitter, name, listener, once, prepend) {
+  checkListener(listener);
+  if (getListeners(emitter).has("newListener")) {
+    emitter.emit("newListener", name, listener);
+  }
+  const listeners = getListeners(emitter).get(name) ?? [];
+  const entry = { listener, once };
+  if (prepend) {
+    listeners.unshift(entry);
+  } else {
+    listeners.push(entry);
+  }
+  getListeners(emitter).set(name, listeners);
+
+  const max = emitter.getMaxListeners();
+  if (max > 0 && listeners.length > max && !listeners.warned) {
+    listeners.warned = true;
+    console.warn(`MaxListenersExceededWarning: Possible EventEmitter memory leak detected. ${listeners.length} ${String(name)} listeners added. Use emitter.setMaxListeners() to increase limit`);
+  }
+  return emitter;
+}
+
+Object.assign(EventEmitter.prototype, {
+  setMaxListeners(n) {
+    this[kMaxListeners] = n;
+    return this;
+  },
+
+  getMaxListeners() {
+    return this[kMaxListeners] ?? EventEmitter.defaultMaxListeners;
+  },
+
+  emit(name, ...args) {
+    const listeners = getListeners(this).get(name);
+    if (name === "error") {
+      getListeners(this).get(EventEmitter.errorMonitor)?.slice().forEach(({ listener }) => listener.apply(this, args));
+      if (!listeners?.length) {
+        throw args[0] instanceof Error ? args[0] : new Error(`Unhandled error. (${args[0]})`);
+      }
+    }
+    if (!listeners?.length) {
+      return false;
+    }
+    for (const entry of listeners.slice()) {
+      if (entry.once) {
+        this.removeListener(name, entry.listener);
+      }
+      entry.listener.apply(this, args);
+    }
+    return true;
+  },
+
+  addListener(name, listener) {
+    return addListener(this, name, listener, false, false);
+  },
+
+  on(name, listener) {
+    return addListener(this, name, listener, false, false);
+  },
+
+  prependListener(name, listener) {
+    return addListener(this, name, listener, false, true);
+  },
+
+  once(name, listener) {
+    return addListener(this, name, listener, true, false);
+  },
+
+  prependOnceListener(name, listener) {
+    return addListener(this, name, listener, true, true);
+  },
+
+  removeListener(name, listener) {
+    checkListener(listener);
+    const listeners = getListeners(this).get(name);
+    if (!listeners) {
+      return this;
+    }
+    const index = listeners.findLastIndex((entry) => entry.listener === listener);
+    if (index !== -1) {
+      listeners.splice(index, 1);
+      if (listeners.length === 0) {
+        getListeners(this).delete(name);
+      }
+      if (getListeners(this).has("removeListener")) {
+        this.emit("removeListener", name, listener);
+      }
+    }
+    return this;
+  },
+
+  off(name, listener) {
+    return this.removeListener(name, listener);
+  },
+
+  removeAllListeners(name) {
+    if (name === undefined) {
+      getListeners(this).clear();
+    } else {
+      getListeners(this).delete(name);
+    }
+    return this;
+  },
+
+  listeners(name) {
+    return (getListeners(this).get(name) ?? []).map((entry) => entry.listener);
+  },
+
+  rawListeners(name) {
+    return this.listeners(name);
+  },
+
+  listenerCount(name) {
+    return getListeners(this).get(name)?.length ?? 0;
+  },
+
+  eventNames() {
+    return [...getListeners(this).keys()];
+  },
+});
+
+EventEmitter.EventEmitter = EventEmitter;
+
+// Resolves with the arguments of the next `name` event, or rejects on an `error` event first
+export function once(emitter, name) {
+  return new Promise((resolve, reject) => {
+    const onError = (error) => {
+      emitter.removeListener(name, onEvent);
+      reject(error);
+    };
+    const onEvent = (...args) => {
+      if (name !== "error") {
+        emitter.removeListener("error", onError);
+      }
+      resolve(args);
+    };
+    emitter.once(name, onEvent);
+    if (name !== "error") {
+      emitter.once("error", onError);
+    }
+  });
+}
+
+EventEmitter.once = once;
+
+export function listenerCount(emitter, name) {
+  return emitter.listenerCount(name);
+}
+
+export const defaultMaxListeners = EventEmitter.defaultMaxListeners;
+
+export { EventEmitter as "module.exports" };
+export default EventEmitter;
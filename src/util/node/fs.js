@@ -0,0 +1,404 @@
+// node:fs
+
+import { Buffer } from "node:buffer";
+import { fileURLToPath } from "node:url";
+
+const errorCodes = {
+  NotFound: ["ENOENT", -2],
+  AlreadyExists: ["EEXIST", -17],
+  PermissionDenied: ["EACCES", -13],
+  NotADirectory: ["ENOTDIR", -20],
+  IsADirectory: ["EISDIR", -21],
+  DirectoryNotEmpty: ["ENOTEMPTY", -39],
+  InvalidData: ["EINVAL", -22],
+  Busy: ["EBUSY", -16],
+};
+
+// Gives runtime errors the `code` and `errno` Node code checks for
+function toNodeError(error, syscall, path) {
+  const [code, errno] = errorCodes[error?.name] ?? [];
+  if (code === undefined) {
+    return error;
+  }
+  const nodeError = new Error(`${code}: ${error.message}, ${syscall}${path === undefined ? "" : ` '${path}'`}`);
+  return Object.assign(nodeError, { code, errno, syscall, path, cause: error });
+}
+
+function wrapSync(syscall, fn) {
+  return (path, ...args) => {
+    try {
+      return fn(toPath(path), ...args);
+    } catch (error) {
+      throw toNodeError(error, syscall, toPath(path));
+    }
+  };
+}
+
+function toPath(path) {
+  if (path instanceof URL) {
+    return fileURLToPath(path);
+  }
+  if (path instanceof Uint8Array) {
+    return new TextDecoder().decode(path);
+  }
+  return path;
+}
+
+function getEncoding(options) {
+  return typeof options === "string" ? options : options?.encoding ?? null;
+}
+
+function toBytes(data, options) {
+  if (typeof data === "string") {
+    return Buffer.from(data, getEncoding(options) ?? "utf8");
+  }
+  if (ArrayBuffer.isView(data)) {
+    return new Uint8Array(data.buffer, data.byteOffset, data.byteLength);
+  }
+  return Buffer.from(String(data));
+}
+
+export class Stats {
+  constructor(info) {
+    this.dev = info.dev ?? 0;
+    this.ino = info.ino ?? 0;
+    this.mode = info.mode ?? 0;
+    this.nlink = info.nlink ?? 0;
+    this.uid = info.uid ?? 0;
+    this.gid = info.gid ?? 0;
+    this.rdev = info.rdev ?? 0;
+    this.size = info.size;
+    this.blksize = info.blksize ?? 0;
+    this.blocks = info.blocks ?? 0;
+    this.mtime = info.mtime ?? new Date(0);
+    this.atime = info.atime ?? new Date(0);
+    this.ctime = info.mtime ?? new Date(0);
+    this.birthtime = info.birthtime ?? new Date(0);
+    this.mtimeMs = this.mtime.getTime();
+    this.atimeMs = this.atime.getTime();
+    this.ctimeMs = this.ctime.getTime();
+    this.birthtimeMs = this.birthtime.getTime();
+    Object.defineProperty(this, "info", { value: info, enumerable: false });
+  }
+
+  isFile() {
+    return this.info.isFile;
+  }
+
+  isDirectory() {
+    return this.info.isDirectory;
+  }
+
+  isSymbolicLink() {
+    return this.info.isSymlink;
+  }
+
+  isBlockDevice() {
+    return this.info.isBlockDevice ?? false;
+  }
+
+  isCharacterDevice() {
+    return this.info.isCharDevice ?? false;
+  }
+
+  isFIFO() {
+    return this.info.isFifo ?? false;
+  }
+
+  isSocket() {
+    return this.info.isSocket ?? false;
+  }
+}
+
+export class Dirent {
+  constructor(entry, parentPath) {
+    this.name = entry.name;
+    this.parentPath = parentPath;
+    this.path = parentPath;
+    Object.defineProperty(this, "entry", { value: entry, enumerable: false });
+  }
+
+  isFile() {
+    return this.entry.isFile;
+  }
+
+  isDirectory() {
+    return this.entry.isDirectory;
+  }
+
+  isSymbolicLink() {
+    return this.entry.isSymlink;
+  }
+
+  isBlockDevice() {
+    return false;
+  }
+
+  isCharacterDevice() {
+    return false;
+  }
+
+  isFIFO() {
+    return false;
+  }
+
+  isSocket() {
+    return false;
+  }
+}
+
+export const constants = {
+  F_OK: 0,
+  R_OK: 4,
+  W_OK: 2,
+  X_OK: 1,
+  COPYFILE_EXCL: 1,
+};
+
+export const existsSync = (path) => {
+  try {
+    system.lstatSync(toPath(path));
+    return true;
+  } catch {
+    return false;
+  }
+};
+
+export const readFileSync = wrapSync("open", (path, options) => {
+  const data = Buffer.from(system.readFileSync(path));
+  const encoding = getEncoding(options);
+  return encoding ? data.toString(encoding) : data;
+});
+
+export const writeFileSync = wrapSync("open", (path, data, options) => {
+  system.writeFileSync(path, toBytes(data, options), { mode: options?.mode, append: options?.flag?.startsWith("a") });
+});
+
+export const appendFileSync = wrapSync("open", (path, data, options) => {
+  system.writeFileSync(path, toBytes(data, options), { mode: options?.mode, append: true });
+});
+
+export const statSync = wrapSync("stat", (path, options) => {
+  try {
+    return new Stats(system.statSync(path));
+  } catch (error) {
+    if (options?.throwIfNoEntry === false && error.name === "NotFound") {
+      return undefined;
+    }
+    throw error;
+  }
+});
+
+export const lstatSync = wrapSync("lstat", (path, options) => {
+  try {
+    return new Stats(system.lstatSync(path));
+  } catch (error) {
+    if (options?.throwIfNoEntry === false && error.name === "NotFound") {
+      return undefined;
+    }
+    throw error;
+  }
+});
+
+export const readdirSync = wrapSync("scandir", (path, options) => {
+  const entries = [...system.readDirSync(path)].sort((a, b) => a.name < b.name ? -1 : a.name > b.name ? 1 : 0);
+  if (options?.withFileTypes) {
+    return entries.map((entry) => new Dirent(entry, path));
+  }
+  return entries.map((entry) => entry.name);
+});
+
+export const mkdirSync = wrapSync("mkdir", (path, options) => {
+  const recursive = typeof options === "object" && options?.recursive;
+  const mode = typeof options === "object" ? options?.mode : options;
+  const existed = recursive && existsSync(path);
+  system.mkdirSync(path, { recursive, mode });
+  return recursive && !existed ? path : undefined;
+});
+
+export const mkdtempSync = wrapSync("mkdtemp", (prefix) => {
+  const separator = prefix.lastIndexOf("/");
+  return system.makeTempDirSync({
+    dir: separator === -1 ? "." : prefix.slice(0, separator) || "/",
+    prefix: prefix.slice(separator + 1),
+  });
+});
+
+export const rmSync = wrapSync("rm", (path, options) => {
+  try {
+    system.removeSync(path, { recursive: options?.recursive ?? false });
+  } catch (error) {
+    if (!(options?.force && error.name === "NotFound")) {
+      throw error;
+    }
+  }
+});
+
+export const rmdirSync = wrapSync("rmdir", (path, options) => {
+  if (!system.statSync(path).isDirectory) {
+    throw Object.assign(new Error(`Not a directory: ${path}`), { name: "NotADirectory" });
+  }
+  system.removeSync(path, { recursive: options?.recursive ?? false });
+});
+
+export const unlinkSync = wrapSync("unlink", (path) => {
+  system.removeSync(path);
+});
+
+export const renameSync = wrapSync("rename", (oldPath, newPath) => {
+  system.renameSync(oldPath, toPath(newPath));
+});
+
+export const copyFileSync = wrapSync("copyfile", (src, dest, mode = 0) => {
+  if (mode & constants.COPYFILE_EXCL && existsSync(dest)) {
+    throw Object.assign(new Error(`File exists: ${toPath(dest)}`), { name: "AlreadyExists" });
+  }
+  system.copyFileSync(src, toPath(dest));
+});
+
+export const realpathSync = wrapSync("realpath", (path) => system.realPathSync(path));
+realpathSync.native = realpathSync;
+
+export const symlinkSync = wrapSync("symlink", (target, path, type) => {
+  system.symlinkSync(target, toPath(path), type ? { type: type === "junction" ? "dir" : type } : undefined);
+});
+
+export const readlinkSync = wrapSync("readlink", (path) => system.readLinkSync(path));
+
+export const chmodSync = wrapSync("chmod", (path, mode) => {
+  system.chmodSync(path, typeof mode === "string" ? parseInt(mode, 8) : mode);
+});
+
+export const utimesSync = wrapSync("utime", (path, atime, mtime) => {
+  system.utimeSync(path, atime, mtime);
+});
+
+export const truncateSync = wrapSync("open", (path, length) => {
+  system.truncateSync(path, length);
+});
+
+export const accessSync = wrapSync("access", (path, mode = constants.F_OK) => {
+  const info = system.statSync(path);
+  if (mode !== constants.F_OK && info.mode !== null) {
+    const uid = system.uid();
+    const shift = uid === info.uid ? 6 : system.gid() === info.gid ? 3 : 0;
+    if (uid !== 0 && ((info.mode >> shift) & mode) !== mode) {
+      throw Object.assign(new Error(`Permission denied: ${path}`), { name: "PermissionDenied" });
+    }
+  }
+});
+
+// Runs a synchronous implementation and reports the result through a Node style callback
+function callbackify(fn) {
+  return (...args) => {
+    const callback = args.pop();
+    if (typeof callback !== "function") {
+      throw new TypeError(`The "cb" argument must be of type function. Received type ${typeof callback}`);
+    }
+    queueMicrotask(() => {
+      let result;
+      try {
+        result = fn(...args);
+      } catch (error) {
+        callback(error);
+        return;
+      }
+      callback(null, result);
+    });
+  };
+}
+
+function promisify(fn) {
+  return (...args) => new Promise((resolve, reject) => {
+    try {
+      resolve(fn(...args));
+    } catch (error) {
+      reject(error);
+    }
+  });
+}
+
+const syncFunctions = {
+  readFile: readFileSync,
+  writeFile: writeFileSync,
+  appendFile: appendFileSync,
+  stat: statSync,
+  lstat: lstatSync,
+  readdir: readdirSync,
+  mkdir: mkdirSync,
+  mkdtemp: mkdtempSync,
+  rm: rmSync,
+  rmdir: rmdirSync,
+  unlink: unlinkSync,
+  rename: renameSync,
+  copyFile: copyFileSync,
+  realpath: realpathSync,
+  symlink: symlinkSync,
+  readlink: readlinkSync,
+  chmod: chmodSync,
+  utimes: utimesSync,
+  truncate: truncateSync,
+  access: accessSync,
+};
+
+export const readFile = callbackify(readFileSync);
+export const writeFile = callbackify(writeFileSync);
+export const appendFile = callbackify(appendFileSync);
+export const stat = callbackify(statSync);
+export const lstat = callbackify(lstatSync);
+export const readdir = callbackify(readdirSync);
+export const mkdir = callbackify(mkdirSync);
+export const mkdtemp = callbackify(mkdtempSync);
+export const rm = callbackify(rmSync);
+export const rmdir = callbackify(rmdirSync);
+export const unlink = callbackify(unlinkSync);
+export const rename = callbackify(renameSync);
+export const copyFile = callbackify(copyFileSync);
+export const realpath = callbackify(realpathSync);
+export const symlink = callbackify(symlinkSync);
+export const readlink = callbackify(readlinkSync);
+export const chmod = callbackify(chmodSync);
+export const utimes = callbackify(utimesSync);
+export const truncate = callbackify(truncateSync);
+export const access = callbackify(accessSync);
+
+// The callback of `exists` takes no error argument
+export function exists(path, callback) {
+  queueMicrotask(() => callback(existsSync(path)));
+}
+
+export const promises = Object.fromEntries(Object.entries(syncFunctions).map(([name, fn]) => [name, promisify(fn)]));
+promises.constants = constants;
+
+const fs = {
+  constants,
+  Stats,
+  Dirent,
+  existsSync,
+  exists,
+  promises,
+  ...Object.fromEntries(Object.entries(syncFunctions).map(([name, fn]) => [`${name}Sync`, fn])),
+  readFile,
+  writeFile,
+  appendFile,
+  stat,
+  lstat,
+  readdir,
+  mkdir,
+  mkdtemp,
+  rm,
+  rmdir,
+  unlink,
+  rename,
+  copyFile,
+  realpath,
+  symlink,
+  readlink,
+  chmod,
+  utimes,
+  truncate,
+  access,
+};
+
+export { fs as "module.exports" };
+export default fs;
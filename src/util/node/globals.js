@@ -0,0 +1,12 @@
+// node:internal/globals
+//
+// Evaluated before the main module when Node globals are enabled
+
+import process from "node:process";
+import { Buffer } from "node:buffer";
+
+Object.defineProperties(globalThis, {
+  process: { value: process, writable: true, configurable: true },
+  Buffer: { value: Buffer, writable: true, configurable: true },
+  global: { value: globalThis, writable: true, configurable: true },
+});
@@ -0,0 +1,26 @@
+use deno_runtime::deno_core::ModuleSpecifier;
+
+/// Evaluated before the main module to expose `process`, `Buffer` and `global`
+pub const GLOBALS_SPECIFIER: &str = "node:internal/globals";
+
+/// Compatibility modules for the Node built-ins, written on top of the `system` namespace
+const BUILTINS: &[(&str, &str)] = &[
+    ("buffer", include_str!("buffer.js")),
+    ("child_process", include_str!("child_process.js")),
+    ("events", include_str!("events.js")),
+    ("fs", include_str!("fs.js")),
+    ("path", include_str!("path.js")),
+    ("process", include_str!("process.js")),
+    ("url", include_str!("url.js")),
+    ("internal/globals", include_str!("globals.js")),
+];
+
+/// The source of a `node:` specifier's compatibility module
+pub fn builtin_source(specifier: &ModuleSpecifier) -> Option<&'static str> {
+    BUILTINS.iter().find(|(name, _)| *name == specifier.path()).map(|(_, source)| *source)
+}
+
+/// Whether a bare specifier such as `fs` names a built-in, which packages may import without the `node:` prefix
+pub fn is_builtin(name: &str) -> bool {
+    !name.starts_with("internal/") && BUILTINS.iter().any(|(builtin, _)| *builtin == name)
+}
@@ -0,0 +1,159 @@
+// node:path
+//
+// Paths are always handled with POSIX semantics, which Windows also accepts for most purposes
+
+export const sep = "/";
+export const delimiter = ":";
+
+function assertPath(path) {
+  if (typeof path !== "string") {
+    throw new TypeError(`The "path" argument must be of type string. Received type ${typeof path}`);
+  }
+}
+
+// Resolves "." and ".." segments, leading ".." segments are kept for relative paths
+function normalizeSegments(path, allowAboveRoot) {
+  const segments = [];
+  for (const segment of path.split("/")) {
+    if (segment === "" || segment === ".") {
+      continue;
+    }
+    if (segment === "..") {
+      if (segments.length > 0 && segments[segments.length - 1] !== "..") {
+        segments.pop();
+      } else if (allowAboveRoot) {
+        segments.push("..");
+      }
+    } else {
+      segments.push(segment);
+    }
+  }
+  return segments.join("/");
+}
+
+export function isAbsolute(path) {
+  assertPath(path);
+  return path.startsWith("/");
+}
+
+export function normalize(path) {
+  assertPath(path);
+  if (path.length === 0) {
+    return ".";
+  }
+  const absolute = isAbsolute(path);
+  const trailingSeparator = path.endsWith("/");
+  let normalized = normalizeSegments(path, !absolute);
+  if (normalized.length === 0 && !absolute) {
+    normalized = ".";
+  }
+  if (normalized.length > 0 && trailingSeparator) {
+    normalized += "/";
+  }
+  return absolute ? `/${normalized}` : normalized;
+}
+
+export function join(...paths) {
+  paths.forEach(assertPath);
+  const joined = paths.filter((path) => path.length > 0).join("/");
+  return joined.length === 0 ? "." : normalize(joined);
+}
+
+export function resolve(...paths) {
+  let resolved = "";
+  for (let i = paths.length - 1; i >= -1 && !resolved.startsWith("/"); i--) {
+    const path = i >= 0 ? paths[i] : system.cwd();
+    assertPath(path);
+    if (path.length > 0) {
+      resolved = resolved.length > 0 ? `${path}/${resolved}` : path;
+    }
+  }
+  return `/${normalizeSegments(resolved, false)}`;
+}
+
+export function relative(from, to) {
+  assertPath(from);
+  assertPath(to);
+  const fromSegments = resolve(from).split("/").filter(Boolean);
+  const toSegments = resolve(to).split("/").filter(Boolean);
+  let common = 0;
+  while (common < fromSegments.length && common < toSegments.length && fromSegments[common] === toSegments[common]) {
+    common++;
+  }
+  return [...fromSegments.slice(common).map(() => ".."), ...toSegments.slice(common)].join("/");
+}
+
+export function dirname(path) {
+  assertPath(path);
+  const trimmed = path.replace(/(?<=.)\/+$/, "");
+  const index = trimmed.lastIndexOf("/");
+  if (index === -1) {
+    return ".";
+  }
+  return index === 0 ? "/" : trimmed.slice(0, index).replace(/(?<=.)\/+$/, "");
+}
+
+export function basename(path, suffix) {
+  assertPath(path);
+  const trimmed = path.replace(/(?<=.)\/+$/, "");
+  let base = trimmed === "/" ? "" : trimmed.slice(trimmed.lastIndexOf("/") + 1);
+  if (suffix !== undefined && base.endsWith(suffix) && base !== suffix) {
+    base = base.slice(0, base.length - suffix.length);
+  }
+  return base;
+}
+
+export function extname(path) {
+  const base = basename(path);
+  const index = base.lastIndexOf(".");
+  return index <= 0 || base === ".." ? "" : base.slice(index);
+}
+
+export function parse(path) {
+  assertPath(path);
+  const root = isAbsolute(path) ? "/" : "";
+  const base = basename(path);
+  const ext = extname(path);
+  const dir = path.length === 0 ? "" : dirname(path);
+  return {
+    root,
+    dir: dir === "." && !path.startsWith(".") ? "" : dir,
+    base,
+    ext,
+    name: ext.length > 0 ? base.slice(0, -ext.length) : base,
+  };
+}
+
+export function format({ root = "", dir, base, name = "", ext = "" }) {
+  const file = base ?? `${name}${ext && !ext.startsWith(".") ? "." : ""}${ext}`;
+  if (!dir) {
+    return `${root}${file}`;
+  }
+  return dir === root ? `${dir}${file}` : `${dir}/${file}`;
+}
+
+export function toNamespacedPath(path) {
+  return path;
+}
+
+const path = {
+  sep,
+  delimiter,
+  isAbsolute,
+  normalize,
+  join,
+  resolve,
+  relative,
+  dirname,
+  basename,
+  extname,
+  parse,
+  format,
+  toNamespacedPath,
+};
+path.posix = path;
+
+export const posix = path;
+
+export { path as "module.exports" };
+export default path;
@@ -0,0 +1,233 @@
+// node:process
+
+import { EventEmitter } from "node:events";
+
+const encoder = new TextEncoder();
+const startTime = performance.now();
+
+function writeAll(resource, chunk) {
+  let bytes = typeof chunk === "string" ? encoder.encode(chunk) : chunk;
+  while (bytes.length > 0) {
+    bytes = bytes.subarray(resource.writeSync(bytes));
+  }
+}
+
+function createWritable(resource, fd) {
+  const stream = new EventEmitter();
+  return Object.assign(stream, {
+    fd,
+    writable: true,
+    get isTTY() {
+      return resource.isTerminal();
+    },
+    get columns() {
+      return resource.isTerminal() ? system.consoleSize().columns : undefined;
+    },
+    get rows() {
+      return resource.isTerminal() ? system.consoleSize().rows : undefined;
+    },
+    write(chunk, encoding, callback) {
+      callback = typeof encoding === "function" ? encoding : callback;
+      writeAll(resource, chunk);
+      if (callback) {
+        queueMicrotask(callback);
+      }
+      return true;
+    },
+    end(chunk, encoding, callback) {
+      if (chunk !== undefined && typeof chunk !== "function") {
+        stream.write(chunk, encoding);
+      }
+      callback = [chunk, encoding, callback].find((arg) => typeof arg === "function");
+      if (callback) {
+        queueMicrotask(callback);
+      }
+      return stream;
+    },
+  });
+}
+
+const stdin = Object.assign(new EventEmitter(), {
+  fd: 0,
+  readable: true,
+  get isTTY() {
+    return system.stdin.isTerminal();
+  },
+  setRawMode(mode) {
+    system.stdin.setRaw(mode);
+    return stdin;
+  },
+  [Symbol.asyncIterator]() {
+    return system.stdin.readable[Symbol.asyncIterator]();
+  },
+});
+
+// Reading starts with the first "data" listener so scripts that never read stdin can exit
+stdin.on("newListener", function start(name) {
+  if (name !== "data") {
+    return;
+  }
+  stdin.removeListener("newListener", start);
+  queueMicrotask(async () => {
+    for await (const chunk of system.stdin.readable) {
+      stdin.emit("data", chunk);
+    }
+    stdin.emit("end");
+    stdin.emit("close");
+  });
+});
+
+const env = new Proxy({}, {
+  get(_target, name) {
+    return typeof name === "string" ? system.env.get(name) : undefined;
+  },
+  set(_target, name, value) {
+    system.env.set(String(name), String(value));
+    return true;
+  },
+  has(_target, name) {
+    return typeof name === "string" && system.env.has(name);
+  },
+  deleteProperty(_target, name) {
+    system.env.delete(String(name));
+    return true;
+  },
+  ownKeys() {
+    return Object.keys(system.env.toObject());
+  },
+  getOwnPropertyDescriptor(_target, name) {
+    const value = typeof name === "string" ? system.env.get(name) : undefined;
+    return value === undefined ? undefined : { value, writable: true, enumerable: true, configurable: true };
+  },
+});
+
+const platforms = { windows: "win32" };
+const architectures = { x86_64: "x64", aarch64: "arm64" };
+
+function hrtime(previous) {
+  const now = performance.now();
+  let seconds = Math.floor(now / 1000);
+  let nanoseconds = Math.floor((now % 1000) * 1e6);
+  if (previous) {
+    seconds -= previous[0];
+    nanoseconds -= previous[1];
+    if (nanoseconds < 0) {
+      seconds -= 1;
+      nanoseconds += 1e9;
+    }
+  }
+  return [seconds, nanoseconds];
+}
+
+hrtime.bigint = () => BigInt(Math.floor(performance.now() * 1e6));
+
+const process = new EventEmitter();
+
+Object.assign(process, {
+  title: "sjs",
+  argv: [system.execPath(), ...system.args],
+  argv0: "sjs",
+  execArgv: [],
+  env,
+  pid: system.pid,
+  ppid: system.ppid,
+  platform: platforms[system.build.os] ?? system.build.os,
+  arch: architectures[system.build.arch] ?? system.build.arch,
+  // The Node version the compatibility layer is modelled after
+  version: "v20.11.1",
+  versions: { node: "20.11.1", v8: system.version.v8, sjs: system.version.deno },
+  release: { name: "node" },
+  features: {},
+  config: { variables: {} },
+  exitCode: undefined,
+  stdin,
+  stdout: createWritable(system.stdout, 1),
+  stderr: createWritable(system.stderr, 2),
+  hrtime,
+
+  execPath: system.execPath(),
+
+  cwd() {
+    return system.cwd();
+  },
+
+  chdir(directory) {
+    system.chdir(directory);
+  },
+
+  exit(code) {
+    if (code !== undefined) {
+      process.exitCode = code;
+    }
+    emitExit();
+    system.exit(process.exitCode ?? 0);
+  },
+
+  abort() {
+    system.exit(134);
+  },
+
+  kill(pid, signal = "SIGTERM") {
+    system.kill(pid, typeof signal === "number" ? "SIGTERM" : signal);
+    return true;
+  },
+
+  nextTick(callback, ...args) {
+    queueMicrotask(() => callback(...args));
+  },
+
+  memoryUsage() {
+    return system.memoryUsage();
+  },
+
+  uptime() {
+    return (performance.now() - startTime) / 1000;
+  },
+
+  cpuUsage() {
+    return { user: 0, system: 0 };
+  },
+
+  umask() {
+    return 0o22;
+  },
+
+  getuid() {
+    return system.uid();
+  },
+
+  getgid() {
+    return system.gid();
+  },
+
+  emitWarning(warning, type = "Warning") {
+    const message = warning instanceof Error ? warning.message : warning;
+    const name = warning instanceof Error ? warning.name : (typeof type === "object" ? type.type ?? "Warning" : type);
+    if (!process.emit("warning", warning)) {
+      console.error(`(sjs:${system.pid}) ${name}: ${message}`);
+    }
+  },
+});
+
+process.memoryUsage.rss = () => system.memoryUsage().rss;
+
+let exiting = false;
+
+// `system.exit` dispatches "unload" as well, so "exit" is only emitted once
+function emitExit() {
+  if (!exiting) {
+    exiting = true;
+    process.emit("exit", process.exitCode ?? 0);
+  }
+}
+
+globalThis.addEventListener("unload", () => {
+  const running = exiting;
+  emitExit();
+  if (!running && process.exitCode) {
+    system.exit(process.exitCode);
+  }
+});
+
+export { process as "module.exports" };
+export default process;
@@ -0,0 +1,143 @@
+// node:url
+
+const isWindows = system.build.os === "windows";
+
+export const URL = globalThis.URL;
+export const URLSearchParams = globalThis.URLSearchParams;
+
+export function fileURLToPath(url) {
+  const parsed = typeof url === "string" ? new URL(url) : url;
+  if (parsed.protocol !== "file:") {
+    throw new TypeError("The URL must be of scheme file");
+  }
+  if (/%2f/i.test(parsed.pathname) || (isWindows && /%5c/i.test(parsed.pathname))) {
+    throw new TypeError("File URL path must not include encoded / characters");
+  }
+  const pathname = decodeURIComponent(parsed.pathname);
+  if (!isWindows) {
+    if (parsed.hostname !== "") {
+      throw new TypeError(`File URL host must be "localhost" or empty on ${system.build.os}`);
+    }
+    return pathname;
+  }
+  const path = pathname.replace(/\//g, "\\");
+  if (parsed.hostname !== "") {
+    return `\\\\${parsed.hostname}${path}`;
+  }
+  // "/C:/dir" becomes "C:\dir"
+  return path.slice(1);
+}
+
+export function pathToFileURL(path) {
+  let absolute = path;
+  if (isWindows) {
+    absolute = path.replace(/\\/g, "/");
+    if (!/^[A-Za-z]:\//.test(absolute) && !absolute.startsWith("//")) {
+      absolute = `${system.cwd().replace(/\\/g, "/")}/${absolute}`;
+    }
+    if (!absolute.startsWith("/")) {
+      absolute = `/${absolute}`;
+    }
+  } else if (!path.startsWith("/")) {
+    absolute = `${system.cwd()}/${path}`;
+  }
+  const url = new URL("file://");
+  url.pathname = absolute.replace(/%/g, "%25").replace(/\n/g, "%0A").replace(/\r/g, "%0D").replace(/\t/g, "%09").replace(/\?/g, "%3F").replace(/#/g, "%23");
+  return url;
+}
+
+// The legacy object form returned by url.parse() and accepted by url.format()
+export class Url {
+  constructor() {
+    this.protocol = null;
+    this.slashes = null;
+    this.auth = null;
+    this.host = null;
+    this.port = null;
+    this.hostname = null;
+    this.hash = null;
+    this.search = null;
+    this.query = null;
+    this.pathname = null;
+    this.path = null;
+    this.href = null;
+  }
+}
+
+export function parse(urlString, parseQueryString = false) {
+  const result = new Url();
+  let parsed;
+  try {
+    parsed = new URL(urlString);
+  } catch {
+    // Relative references are kept relative
+    parsed = new URL(urlString, "relative:///");
+    result.pathname = urlString.startsWith("/") ? parsed.pathname : decodeURI(parsed.pathname).slice(1);
+  }
+
+  if (parsed.protocol !== "relative:") {
+    result.protocol = parsed.protocol;
+    result.slashes = /^[a-z][a-z0-9+.-]*:\/\//i.test(urlString);
+    result.auth = parsed.username ? decodeURIComponent(parsed.password ? `${parsed.username}:${parsed.password}` : parsed.username) : null;
+    result.host = parsed.host || null;
+    result.port = parsed.port || null;
+    result.hostname = parsed.hostname || null;
+    result.pathname = parsed.pathname || null;
+  }
+  result.hash = parsed.hash || null;
+  result.search = parsed.search || null;
+  result.query = parseQueryString
+    ? Object.fromEntries(new URLSearchParams(parsed.search))
+    : (parsed.search ? parsed.search.slice(1) : null);
+  result.path = (result.pathname ?? "") + (result.search ?? "") || null;
+  result.href = format(result);
+  return result;
+}
+
+export function format(urlObject, options) {
+  if (typeof urlObject === "string") {
+    urlObject = parse(urlObject);
+  } else if (urlObject instanceof URL) {
+    const url = new URL(urlObject.href);
+    if (options?.auth === false) {
+      url.username = "";
+      url.password = "";
+    }
+    if (options?.search === false) {
+      url.search = "";
+    }
+    if (options?.fragment === false) {
+      url.hash = "";
+    }
+    return url.href;
+  }
+
+  const protocol = urlObject.protocol ? (urlObject.protocol.endsWith(":") ? urlObject.protocol : `${urlObject.protocol}:`) : "";
+  const auth = urlObject.auth ? `${encodeURIComponent(urlObject.auth).replace(/%3A/i, ":")}@` : "";
+  const host = urlObject.host ?? (urlObject.hostname ? `${urlObject.hostname}${urlObject.port ? `:${urlObject.port}` : ""}` : "");
+  const slashes = urlObject.slashes || (host && ["http:", "https:", "ftp:", "file:", "ws:", "wss:"].includes(protocol)) ? "//" : "";
+  let search = urlObject.search ?? "";
+  if (!search && urlObject.query && typeof urlObject.query === "object") {
+    const query = new URLSearchParams(urlObject.query).toString();
+    search = query ? `?${query}` : "";
+  }
+  if (search && !search.startsWith("?")) {
+    search = `?${search}`;
+  }
+  const hash = urlObject.hash ? (urlObject.hash.startsWith("#") ? urlObject.hash : `#${urlObject.hash}`) : "";
+  return `${protocol}${slashes}${auth}${host}${urlObject.pathname ?? ""}${search}${hash}`;
+}
+
+export function resolve(from, to) {
+  const resolved = new URL(to, new URL(from, "resolve://"));
+  if (resolved.protocol === "resolve:") {
+    const { pathname, search, hash } = resolved;
+    return from.startsWith("/") ? `${pathname}${search}${hash}` : `${pathname.slice(1)}${search}${hash}`;
+  }
+  return resolved.toString();
+}
+
+const url = { URL, URLSearchParams, Url, fileURLToPath, pathToFileURL, parse, format, resolve };
+
+export { url as "module.exports" };
+export default url;
@@ -0,0 +1,545 @@
+use deno_runtime::deno_core;
+use deno_core::ModuleSpecifier;
+use deno_core::error::{custom_error,generic_error,AnyError};
+use deno_core::parking_lot::Mutex;
+use deno_core::serde_json::{self,Value};
+use deno_core::url::Url;
+
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use semver::{Version,VersionReq};
+use serde::Deserialize;
+use sha2::{Digest,Sha512};
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Component,Path,PathBuf};
+use std::sync::Arc;
+
+use crate::util::{node,CacheSetting,HttpClient,Lockfile};
+
+pub const DEFAULT_NPM_REGISTRY: &str = "https://registry.npmjs.org/";
+
+/// Conditions matched against package.json `exports`. As in Node, the first key of a conditions object that is one
+/// of these wins, so the order of the object decides rather than the order here. That order relies on deno_core
+/// building serde_json with `preserve_order`
+const CONDITIONS: &[&str] = &["sjs", "deno", "node", "import", "default"];
+
+/// Written into each extracted package with the integrity of its tarball, so installed packages can be checked against a lockfile
+const INTEGRITY_FILE: &str = ".sjs-integrity";
+
+/// Extensions tried, in order, for specifiers without one
+const EXTENSIONS: &[&str] = &["js", "json", "cjs", "mjs"];
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum NpmModuleFormat {
+    Esm,
+    CommonJs,
+}
+
+/// The abbreviated package metadata served by npm registries
+#[derive(Deserialize)]
+struct Packument {
+    #[serde(default, rename = "dist-tags")]
+    dist_tags: HashMap<String, String>,
+    #[serde(default)]
+    versions: HashMap<String, PackageVersion>,
+}
+
+#[derive(Deserialize)]
+struct PackageVersion {
+    dist: Dist,
+}
+
+#[derive(Deserialize)]
+struct Dist {
+    tarball: String,
+    integrity: Option<String>,
+}
+
+/// Resolves `npm:` specifiers to files in packages extracted under the storage directory, each
+/// version of a package is kept in `<dir>/<name>/<version>`
+#[derive(Debug)]
+pub struct NpmResolver {
+    dir: PathBuf,
+    registry: String,
+    http_client: Arc<HttpClient>,
+    cache_setting: CacheSetting,
+    allow_remote: bool,
+    lockfile: Option<Arc<Lockfile>>,
+    /// The package directory chosen for each `name@range` so a program sees one version per range
+    installed: Mutex<HashMap<String, PathBuf>>,
+    package_jsons: Mutex<HashMap<PathBuf, Arc<Value>>>,
+    /// Packages downloaded from the registry rather than found in the storage directory
+    downloads: Mutex<Vec<String>>,
+}
+
+impl NpmResolver {
+    pub fn new(dir: PathBuf, registry: String, http_client: Arc<HttpClient>, cache_setting: CacheSetting, allow_remote: bool, lockfile: Option<Arc<Lockfile>>) -> Self {
+        Self {
+            dir,
+            registry,
+            http_client,
+            cache_setting,
+            allow_remote,
+            lockfile,
+            installed: Default::default(),
+            package_jsons: Default::default(),
+            downloads: Default::default(),
+        }
+    }
+
+    /// The packages downloaded so far as `npm:name@version`
+    pub fn downloads(&self) -> Vec<String> {
+        self.downloads.lock().clone()
+    }
+
+    /// Installs the package an `npm:` specifier refers to if needed and returns the file it points to
+    pub async fn resolve_npm_specifier(&self, specifier: &ModuleSpecifier) -> Result<ModuleSpecifier, AnyError> {
+        let (name, range, subpath) = parse_npm_specifier(specifier)?;
+        let package_dir = self.install(&name, &range).await?;
+        let path = self.resolve_package_subpath(&package_dir, subpath.as_deref())?;
+        ModuleSpecifier::from_file_path(&path).map_err(|_| generic_error(format!("{}: Invalid file path", path.display())))
+    }
+
+    /// Resolves imports made by files inside packages the way Node does, bare specifiers become `npm:`
+    /// specifiers for the dependency's range and relative ones may omit extensions and `index.js`.
+    /// Bare specifiers must be declared in the package's dependencies. Returns `None` for referrers outside of packages
+    pub fn resolve_in_package(&self, specifier: &str, referrer: &ModuleSpecifier) -> Result<Option<ModuleSpecifier>, AnyError> {
+        let Some((package_dir, name, version)) = referrer.to_file_path().ok().and_then(|path| self.package_of(&path)) else {
+            return Ok(None);
+        };
+
+        if specifier.starts_with("./") || specifier.starts_with("../") || specifier.starts_with('/') {
+            let resolved = referrer.join(specifier)?;
+            let path = resolved.to_file_path().map_err(|_| generic_error(format!("{}: Invalid file path", resolved)))?;
+            return Ok(Some(match probe(&path) {
+                Some(path) => ModuleSpecifier::from_file_path(&path).map_err(|_| generic_error(format!("{}: Invalid file path", path.display())))?,
+                None => resolved
+            }));
+        }
+        if Url::parse(specifier).is_ok() {
+            return Ok(None);
+        }
+        // Built-ins take precedence over packages of the same name, as in Node
+        if node::is_builtin(specifier) {
+            return Ok(Some(Url::parse(&format!("node:{}", specifier))?));
+        }
+
+        let (dependency, subpath) = split_package_name(specifier);
+        let range = if dependency == name {
+            version
+        } else {
+            // Packages that are not declared would be installed at an arbitrary version, so they are an error as
+            // they are in Node with a strict package manager
+            let package_json = self.package_json(&package_dir);
+            ["dependencies", "peerDependencies", "optionalDependencies"].iter()
+                .find_map(|field| package_json.get(field).and_then(|dependencies| dependencies.get(dependency)).and_then(Value::as_str))
+                .ok_or_else(|| generic_error(format!("Cannot find package '{}' imported from {}, it is not a dependency of npm:{}@{}", dependency, referrer, name, version)))?
+                .to_string()
+        };
+
+        // Aliases such as `"foo": "npm:bar@^1"` name the package to install themselves
+        let target = match range.strip_prefix("npm:") {
+            Some(alias) => format!("npm:{}", alias),
+            None if is_registry_range(&range) => format!("npm:{}@{}", dependency, range),
+            None => format!("npm:{}", dependency)
+        };
+        Ok(Some(Url::parse(&match subpath {
+            Some(subpath) => format!("{}/{}", target, subpath),
+            None => target
+        })?))
+    }
+
+    /// Whether a file in a package is an ES module or CommonJS, following the extension and the
+    /// `type` field of the closest package.json. Returns `None` for files outside of packages
+    pub fn module_format(&self, specifier: &ModuleSpecifier) -> Option<NpmModuleFormat> {
+        let path = specifier.to_file_path().ok()?;
+        let (package_dir, _, _) = self.package_of(&path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("mjs") => return Some(NpmModuleFormat::Esm),
+            Some("cjs") => return Some(NpmModuleFormat::CommonJs),
+            _ => {}
+        }
+
+        let is_module = path.ancestors().skip(1).take_while(|dir| dir.starts_with(&package_dir))
+            .find(|dir| dir.join("package.json").is_file())
+            .map(|dir| self.package_json(dir))
+            .is_some_and(|package_json| package_json.get("type").and_then(Value::as_str) == Some("module"));
+        Some(if is_module { NpmModuleFormat::Esm } else { NpmModuleFormat::CommonJs })
+    }
+
+    /// Finds the directory, name and version of the package a file belongs to
+    fn package_of(&self, path: &Path) -> Option<(PathBuf, String, String)> {
+        let components: Vec<String> = path.strip_prefix(&self.dir).ok()?.components().map(|component| component.as_os_str().to_string_lossy().into_owned()).collect();
+        let (name, version) = match components.as_slice() {
+            [scope, name, version, ..] if scope.starts_with('@') => (format!("{}/{}", scope, name), version.clone()),
+            [name, version, ..] => (name.clone(), version.clone()),
+            _ => return None
+        };
+        Some((self.dir.join(&name).join(&version), name, version))
+    }
+
+    fn package_json(&self, package_dir: &Path) -> Arc<Value> {
+        let path = package_dir.join("package.json");
+        self.package_jsons.lock().entry(path.clone()).or_insert_with(|| {
+            Arc::new(fs::read(&path).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok()).unwrap_or(Value::Null))
+        }).clone()
+    }
+
+    /// Finds the file a package exports at `subpath`, using `exports` when the package has it and `main` otherwise
+    fn resolve_package_subpath(&self, package_dir: &Path, subpath: Option<&str>) -> Result<PathBuf, AnyError> {
+        let package_json = self.package_json(package_dir);
+        let path = match package_json.get("exports").filter(|exports| !exports.is_null()) {
+            Some(exports) => {
+                let key = subpath.map(|subpath| format!("./{}", subpath)).unwrap_or_else(|| String::from("."));
+                let target = resolve_exports(exports, &key).ok_or_else(|| generic_error(format!(
+                    "Package subpath '{}' is not defined by \"exports\" in {}", key, package_dir.join("package.json").display()
+                )))?;
+                package_dir.join(target.trim_start_matches("./"))
+            },
+            None => package_dir.join(subpath.unwrap_or_else(|| package_json.get("main").and_then(Value::as_str).unwrap_or("index.js")))
+        };
+        probe(&path).ok_or_else(|| generic_error(format!("Cannot find module '{}'", path.display())))
+    }
+
+    /// Returns the directory of a version matching `range`, downloading one if none is stored yet
+    async fn install(&self, name: &str, range: &str) -> Result<PathBuf, AnyError> {
+        let key = format!("{}@{}", name, range);
+        if let Some(package_dir) = self.installed.lock().get(&key) {
+            return Ok(package_dir.clone());
+        }
+
+        let package_dir = match self.find_installed(name, range).filter(|_| !self.should_reload(name)) {
+            Some(package_dir) if self.check_installed(name, &package_dir)? => package_dir,
+            _ => self.download(name, range).await?
+        };
+        self.installed.lock().insert(key, package_dir.clone());
+        Ok(package_dir)
+    }
+
+    fn should_reload(&self, name: &str) -> bool {
+        match &self.cache_setting {
+            CacheSetting::ReloadAll => true,
+            CacheSetting::ReloadSome(prefixes) => prefixes.iter().any(|prefix| format!("npm:{}", name).starts_with(prefix.as_str())),
+            _ => false
+        }
+    }
+
+    /// Checks an installed package against the lockfile, packages installed without a recorded integrity
+    /// cannot be checked and need to be downloaded again
+    fn check_installed(&self, name: &str, package_dir: &Path) -> Result<bool, AnyError> {
+        let Some(lockfile) = &self.lockfile else {
+            return Ok(true);
+        };
+        let Ok(integrity) = fs::read_to_string(package_dir.join(INTEGRITY_FILE)) else {
+            return Ok(false);
+        };
+        let version = package_dir.file_name().unwrap_or_default().to_string_lossy();
+        lockfile.check_or_insert_npm(&format!("{}@{}", name, version), integrity.trim())?;
+        Ok(true)
+    }
+
+    /// The highest stored version matching `range`, dist-tags other than `latest` always need the registry
+    fn find_installed(&self, name: &str, range: &str) -> Option<PathBuf> {
+        let range = parse_range(if range == "latest" { "*" } else { range }).ok()?;
+        fs::read_dir(self.dir.join(name)).ok()?
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter_map(|version| Version::parse(&version).ok())
+            .filter(|version| range.iter().any(|req| req.matches(version)))
+            .max()
+            .map(|version| self.dir.join(name).join(version.to_string()))
+    }
+
+    async fn download(&self, name: &str, range: &str) -> Result<PathBuf, AnyError> {
+        if !self.allow_remote {
+            return Err(custom_error("NoRemote", format!("An npm package was requested: \"npm:{name}@{range}\", but --remote is not specified.")));
+        }
+        if self.cache_setting == CacheSetting::Only {
+            return Err(custom_error("NotCached", format!("npm package not found in cache: \"npm:{name}@{range}\", --cached-only is specified.")));
+        }
+
+        let registry = Url::parse(&format!("{}/", self.registry.trim_end_matches('/'))).map_err(|x| generic_error(format!("{}: {}", self.registry, x)))?;
+        let packument_url = registry.join(&name.replace('/', "%2f"))?;
+        let packument = self.http_client.download(packument_url.clone(), Some("application/vnd.npm.install-v1+json; q=1.0, application/json; q=0.8, */*")).await
+            .map_err(|x| generic_error(format!("npm:{}: {}", name, x)))?;
+        let packument: Packument = serde_json::from_slice(&packument).map_err(|x| generic_error(format!("{}: {}", packument_url, x)))?;
+
+        let (version, package_version) = select_version(&packument, name, range)?;
+        let package_dir = self.dir.join(name).join(version.to_string());
+        if package_dir.is_dir() && !self.should_reload(name) && self.check_installed(name, &package_dir)? {
+            return Ok(package_dir);
+        }
+
+        let tarball = self.http_client.download(Url::parse(&package_version.dist.tarball)?, None).await
+            .map_err(|x| generic_error(format!("npm:{}@{}: {}", name, version, x)))?;
+        let integrity = format!("sha512-{}", BASE64_STANDARD.encode(Sha512::digest(&tarball)));
+        if let Some(expected) = package_version.dist.integrity.as_deref().filter(|integrity| integrity.starts_with("sha512-")) {
+            if integrity != expected {
+                return Err(custom_error("Integrity", format!("The tarball of npm:{}@{} does not match its integrity (expected {}, found {})", name, version, expected, integrity)));
+            }
+        }
+        // The registry's integrity only guards against corrupt downloads, the lockfile also catches a republished tarball
+        if let Some(lockfile) = &self.lockfile {
+            lockfile.check_or_insert_npm(&format!("{}@{}", name, version), &integrity)?;
+        }
+
+        extract(&tarball, &integrity, &package_dir).map_err(|x| generic_error(format!("npm:{}@{}: {}", name, version, x)))?;
+        self.downloads.lock().push(format!("npm:{}@{}", name, version));
+        Ok(package_dir)
+    }
+}
+
+/// Splits `npm:name@range/subpath` where the range and subpath are optional
+fn parse_npm_specifier(specifier: &ModuleSpecifier) -> Result<(String, String, Option<String>), AnyError> {
+    let text = specifier.path().trim_start_matches('/').replace("%20", " ");
+    let (package, subpath) = split_package_name(&text);
+    if package.is_empty() {
+        return Err(generic_error(format!("Invalid npm specifier \"{}\"", specifier)));
+    }
+    let (name, range) = match package[1..].find('@') {
+        Some(i) => (&package[..i + 1], &package[i + 2..]),
+        None => (package, "")
+    };
+    if name.ends_with('/') {
+        return Err(generic_error(format!("Invalid npm specifier \"{}\"", specifier)));
+    }
+    Ok((name.to_string(), range.to_string(), subpath.filter(|subpath| !subpath.is_empty()).map(str::to_string)))
+}
+
+/// Splits a package name, which is two segments for scoped packages, from the rest of a bare specifier
+fn split_package_name(specifier: &str) -> (&str, Option<&str>) {
+    let segments = if specifier.starts_with('@') { 2 } else { 1 };
+    match specifier.match_indices('/').nth(segments - 1) {
+        Some((i, _)) => (&specifier[..i], Some(&specifier[i + 1..])),
+        None => (specifier, None)
+    }
+}
+
+/// Dependency ranges can also be URLs, paths or git repositories, which are installed as the latest version instead
+fn is_registry_range(range: &str) -> bool {
+    !range.contains(':') && !range.contains('/')
+}
+
+/// Converts an npm range to Cargo style requirements, one per `||` alternative
+fn parse_range(range: &str) -> Result<Vec<VersionReq>, AnyError> {
+    const OPERATORS: &str = "<>=~^";
+    range.split("||").map(|alternative| {
+        let alternative = alternative.trim();
+        let comparators = match alternative.split_once(" - ") {
+            Some((lower, upper)) => vec![format!(">={}", lower.trim()), format!("<={}", upper.trim())],
+            None => {
+                let mut comparators = vec![];
+                let mut operator = String::new();
+                for token in alternative.split_whitespace() {
+                    if token.chars().all(|c| OPERATORS.contains(c)) {
+                        operator.push_str(token);
+                    } else {
+                        comparators.push(format!("{}{}", std::mem::take(&mut operator), token));
+                    }
+                }
+                comparators
+            }
+        };
+
+        let comparators: Vec<String> = comparators.into_iter().map(|comparator| {
+            let (operator, version) = comparator.split_at(comparator.find(|c| !OPERATORS.contains(c)).unwrap_or(comparator.len()));
+            let version = version.trim_start_matches(['v', '=']);
+            let is_wildcard = version.split(['-', '+']).next().is_some_and(|version| version.contains(['*', 'x', 'X']));
+            // Bare versions are exact in npm but caret requirements in Cargo
+            match operator {
+                "" | "=" if !is_wildcard => format!("={}", version),
+                _ => format!("{}{}", operator, version)
+            }
+        }).collect();
+
+        let req = if comparators.is_empty() { String::from("*") } else { comparators.join(", ") };
+        VersionReq::parse(&req).map_err(|x| generic_error(format!("Invalid version range \"{}\": {}", range, x)))
+    }).collect()
+}
+
+/// Picks the version npm would, the version tagged `latest` if it matches and otherwise the highest that does
+fn select_version<'a>(packument: &'a Packument, name: &str, range: &str) -> Result<(Version, &'a PackageVersion), AnyError> {
+    let find = |version: &Version| packument.versions.iter().find(|(key, _)| Version::parse(key).is_ok_and(|key| &key == version)).map(|(_, package_version)| package_version);
+
+    if let Some(version) = packument.dist_tags.get(if range.is_empty() { "latest" } else { range }).and_then(|version| Version::parse(version).ok()) {
+        if let Some(package_version) = find(&version) {
+            return Ok((version, package_version));
+        }
+    }
+
+    let range_reqs = parse_range(range)?;
+    let matches = |version: &Version| range_reqs.iter().any(|req| req.matches(version));
+    let latest = packument.dist_tags.get("latest").and_then(|version| Version::parse(version).ok()).filter(|version| matches(version));
+    let version = latest.or_else(|| packument.versions.keys().filter_map(|version| Version::parse(version).ok()).filter(|version| matches(version)).max())
+        .ok_or_else(|| generic_error(format!("No version of npm:{} matches \"{}\"", name, range)))?;
+    let package_version = find(&version).ok_or_else(|| generic_error(format!("No version of npm:{} matches \"{}\"", name, range)))?;
+    Ok((version, package_version))
+}
+
+/// Resolves a subpath such as `.` or `./feature` against package.json `exports`
+fn resolve_exports(exports: &Value, subpath: &str) -> Option<String> {
+    // Only an object whose keys start with "." maps subpaths, anything else is the export of "."
+    let subpaths = exports.as_object().filter(|map| map.keys().next().is_some_and(|key| key.starts_with('.')));
+    let Some(subpaths) = subpaths else {
+        return (subpath == ".").then(|| resolve_export_target(exports, None)).flatten();
+    };
+
+    if let Some(target) = subpaths.get(subpath) {
+        return resolve_export_target(target, None);
+    }
+
+    // Patterns contain a single "*", the one with the longest prefix wins
+    subpaths.iter().filter_map(|(key, target)| {
+        let (prefix, suffix) = key.split_once('*')?;
+        let matched = subpath.strip_prefix(prefix)?.strip_suffix(suffix)?;
+        Some((prefix.len(), target, matched))
+    }).max_by_key(|(len, _, _)| *len).and_then(|(_, target, matched)| resolve_export_target(target, Some(matched)))
+}
+
+/// Conditions objects are walked in their own key order, skipping conditions that do not apply
+fn resolve_export_target(target: &Value, matched: Option<&str>) -> Option<String> {
+    match target {
+        Value::String(target) => Some(match matched {
+            Some(matched) => target.replace('*', matched),
+            None => target.clone()
+        }),
+        Value::Array(targets) => targets.iter().find_map(|target| resolve_export_target(target, matched)),
+        Value::Object(conditions) => conditions.iter()
+            .filter(|(condition, _)| CONDITIONS.contains(&condition.as_str()))
+            .find_map(|(_, target)| resolve_export_target(target, matched)),
+        _ => None
+    }
+}
+
+/// Finds the file Node would load for `path`, trying extensions and then the directory's `main` or `index`
+fn probe(path: &Path) -> Option<PathBuf> {
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    let with_extension = |path: &Path, ext: &str| {
+        let mut path = OsString::from(path);
+        path.push(".");
+        path.push(ext);
+        PathBuf::from(path)
+    };
+    if let Some(path) = EXTENSIONS.iter().map(|ext| with_extension(path, ext)).find(|path| path.is_file()) {
+        return Some(path);
+    }
+    if path.is_dir() {
+        let main = fs::read(path.join("package.json")).ok()
+            .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok())
+            .and_then(|package_json| package_json.get("main").and_then(Value::as_str).map(|main| path.join(main)));
+        if let Some(path) = main.filter(|main| main.as_path() != path).and_then(|main| probe(&main)) {
+            return Some(path);
+        }
+        return EXTENSIONS.iter().map(|ext| with_extension(&path.join("index"), ext)).find(|path| path.is_file());
+    }
+    None
+}
+
+/// Unpacks a package tarball, whose files are all in a top level directory, into `package_dir`
+fn extract(tarball: &[u8], integrity: &str, package_dir: &Path) -> Result<(), AnyError> {
+    let parent = package_dir.parent().unwrap();
+    fs::create_dir_all(parent)?;
+
+    // Extract next to the final directory and move it into place so an interrupted download is never used
+    let temp_dir = parent.join(format!(".{}-{}", package_dir.file_name().unwrap().to_string_lossy(), rand::random::<u32>()));
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(tarball));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        if !path.components().all(|component| matches!(component, Component::Normal(_))) {
+            continue;
+        }
+        let path = temp_dir.join(path.components().skip(1).collect::<PathBuf>());
+        fs::create_dir_all(path.parent().unwrap())?;
+        entry.unpack(&path)?;
+    }
+    fs::create_dir_all(&temp_dir)?;
+    fs::write(temp_dir.join(INTEGRITY_FILE), integrity)?;
+
+    if package_dir.exists() {
+        fs::remove_dir_all(package_dir)?;
+    }
+    fs::rename(&temp_dir, package_dir).or_else(|err| if package_dir.is_dir() {
+        // Another process extracted the same version first
+        fs::remove_dir_all(&temp_dir)
+    } else {
+        Err(err)
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn matches(range: &str, version: &str) -> bool {
+        let version = Version::parse(version).unwrap();
+        parse_range(range).unwrap().iter().any(|req| req.matches(&version))
+    }
+
+    #[test]
+    fn parses_npm_specifiers() {
+        let parse = |specifier: &str| parse_npm_specifier(&Url::parse(specifier).unwrap()).unwrap();
+        assert_eq!(parse("npm:preact"), (String::from("preact"), String::new(), None));
+        assert_eq!(parse("npm:preact@10.5.0/hooks"), (String::from("preact"), String::from("10.5.0"), Some(String::from("hooks"))));
+        assert_eq!(parse("npm:@scope/package@^1.0.0"), (String::from("@scope/package"), String::from("^1.0.0"), None));
+        assert_eq!(parse("npm:@scope/package/sub/path"), (String::from("@scope/package"), String::new(), Some(String::from("sub/path"))));
+        assert!(parse_npm_specifier(&Url::parse("npm:").unwrap()).is_err());
+    }
+
+    #[test]
+    fn bare_versions_are_exact() {
+        assert!(matches("1.2.3", "1.2.3"));
+        assert!(!matches("1.2.3", "1.2.4"));
+        assert!(matches("=v1.2.3", "1.2.3"));
+    }
+
+    #[test]
+    fn parses_npm_ranges() {
+        assert!(matches("", "3.0.0"));
+        assert!(matches("^1.2.0", "1.9.0"));
+        assert!(!matches("^1.2.0", "2.0.0"));
+        assert!(matches("1.x", "1.4.0"));
+        assert!(matches(">= 1.0.0 < 2.0.0", "1.5.0"));
+        assert!(!matches(">= 1.0.0 < 2.0.0", "2.0.0"));
+        assert!(matches("1.0.0 - 1.2.0", "1.2.0"));
+        assert!(!matches("1.0.0 - 1.2.0", "1.2.1"));
+        assert!(matches("^1.0.0 || ^3.0.0", "3.1.0"));
+        assert!(!matches("^1.0.0 || ^3.0.0", "2.0.0"));
+        assert!(parse_range("not a range").is_err());
+    }
+
+    #[test]
+    fn matches_conditions_in_key_order() {
+        assert_eq!(resolve_exports(&json!({ "default": "./index.js", "import": "./index.mjs" }), "."), Some(String::from("./index.js")));
+        assert_eq!(resolve_exports(&json!({ "node": "./node.js", "deno": "./deno.js" }), "."), Some(String::from("./node.js")));
+        assert_eq!(resolve_exports(&json!({ "browser": "./browser.js", "import": { "types": "./index.d.ts", "default": "./index.mjs" } }), "."), Some(String::from("./index.mjs")));
+    }
+
+    #[test]
+    fn resolves_exports() {
+        assert_eq!(resolve_exports(&json!("./index.js"), "."), Some(String::from("./index.js")));
+        assert_eq!(resolve_exports(&json!("./index.js"), "./feature"), None);
+        assert_eq!(resolve_exports(&json!({ "require": "./index.cjs", "import": "./index.mjs" }), "."), Some(String::from("./index.mjs")));
+
+        let exports = json!({
+            ".": "./index.js",
+            "./feature": { "require": "./feature.cjs", "default": "./feature.js" },
+            "./utils/*": "./dist/utils/*.js",
+            "./utils/internal/*": null
+        });
+        assert_eq!(resolve_exports(&exports, "."), Some(String::from("./index.js")));
+        assert_eq!(resolve_exports(&exports, "./feature"), Some(String::from("./feature.js")));
+        assert_eq!(resolve_exports(&exports, "./utils/math"), Some(String::from("./dist/utils/math.js")));
+        assert_eq!(resolve_exports(&exports, "./utils/internal/secret"), None);
+        assert_eq!(resolve_exports(&exports, "./missing"), None);
+    }
+}
@@ -0,0 +1,134 @@
+use deno_runtime::deno_core;
+use deno_core::{ModuleSpecifier,SourceMapGetter};
+use deno_core::parking_lot::Mutex;
+
+use sourcemap::{SourceMap,SourceMapBuilder};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path,PathBuf};
+use std::sync::Arc;
+
+use crate::util::includes::expand_includes;
+
+/// How far ahead to look for a compiled line in the original source before treating it as rewritten
+const LOOKAHEAD: usize = 200;
+
+/// The source maps and original sources of compiled modules, shared by every module loader of a program
+#[derive(Clone, Default)]
+pub struct SourceMapStore {
+    maps: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    sources: Arc<Mutex<HashMap<String, Arc<str>>>>,
+}
+
+impl SourceMapStore {
+    pub fn insert(&self, specifier: &ModuleSpecifier, source_map: &[u8]) {
+        if let Ok(parsed) = SourceMap::from_slice(source_map) {
+            let mut sources = self.sources.lock();
+            for (i, source) in parsed.sources().enumerate() {
+                if let Some(contents) = parsed.get_source_contents(i as u32) {
+                    sources.insert(source.to_string(), contents.into());
+                }
+            }
+        }
+        self.maps.lock().insert(specifier.to_string(), source_map.to_vec());
+    }
+}
+
+impl SourceMapGetter for SourceMapStore {
+    fn get_source_map(&self, file_name: &str) -> Option<Vec<u8>> {
+        self.maps.lock().get(file_name).cloned()
+    }
+
+    fn get_source_line(&self, file_name: &str, line_number: usize) -> Option<String> {
+        self.sources.lock().get(file_name).and_then(|source| source.split('\n').nth(line_number)).map(|line| line.trim_end_matches('\r').to_string())
+    }
+}
+
+/// Maps compiled code back to the original source and any files it `#include`s (when `include_paths` is given)
+///
+/// mtsc only returns the compiled text, so lines are matched up by content: each compiled line is looked
+/// for a little way ahead in the original with its includes expanded. Matching lines are mapped token by
+/// token, lines that were rewritten (types stripped, macros expanded) map to the next unmatched line.
+pub fn create_source_map(specifier: &ModuleSpecifier, original: &str, compiled: &str, include_paths: Option<&[String]>) -> Result<Vec<u8>, sourcemap::Error> {
+    // Without a directory or include paths nothing can be included, so the original is used as is
+    let (dir, include_paths) = match include_paths {
+        Some(include_paths) => (specifier.to_file_path().ok().filter(|_| specifier.scheme() == "file").and_then(|path| path.parent().map(Path::to_path_buf)), include_paths),
+        None => (None, &[][..])
+    };
+    let expanded = expand_includes(original, dir.as_deref(), include_paths);
+
+    let mut builder = SourceMapBuilder::new(Some(specifier.as_str()));
+    let main_id = builder.add_source(specifier.as_str());
+    builder.set_source_contents(main_id, Some(original));
+
+    let mut source_ids: HashMap<PathBuf, u32> = HashMap::new();
+    let mut source_id = |builder: &mut SourceMapBuilder, file: &Option<PathBuf>| match file {
+        None => main_id,
+        Some(path) => *source_ids.entry(path.clone()).or_insert_with(|| {
+            let url = ModuleSpecifier::from_file_path(path).map(|url| url.to_string()).unwrap_or_else(|_| path.display().to_string());
+            let id = builder.add_source(&url);
+            builder.set_source_contents(id, fs::read_to_string(path).ok().as_deref());
+            id
+        })
+    };
+
+    let mut next = 0;
+    for (dst_line, text) in compiled.lines().enumerate() {
+        let trimmed = text.trim();
+        if trimmed.is_empty() || next >= expanded.len() {
+            continue;
+        }
+
+        let window = &expanded[next..(next + LOOKAHEAD).min(expanded.len())];
+        match window.iter().position(|line| line.text.trim() == trimmed) {
+            Some(offset) => {
+                let line = &expanded[next + offset];
+                let id = source_id(&mut builder, &line.file);
+                let dst_indent = utf16_len(&text[..text.len() - text.trim_start().len()]);
+                let src_indent = utf16_len(&line.text[..line.text.len() - line.text.trim_start().len()]);
+                for column in token_starts(trimmed) {
+                    builder.add_raw(dst_line as u32, dst_indent + column, line.line as u32, src_indent + column, Some(id), None, false);
+                }
+                next += offset + 1;
+            },
+            None => {
+                // Directives and blank lines never produce code, so the rewritten line is the next one that could have
+                let Some(line) = window.iter().find(|line| !line.text.trim().is_empty() && !line.text.trim_start().starts_with('#')) else {
+                    continue;
+                };
+                let id = source_id(&mut builder, &line.file);
+                let src_indent = utf16_len(&line.text[..line.text.len() - line.text.trim_start().len()]);
+                builder.add_raw(dst_line as u32, 0, line.line as u32, src_indent, Some(id), None, false);
+            }
+        }
+    }
+
+    let mut source_map = vec![];
+    builder.into_sourcemap().to_writer(&mut source_map)?;
+    Ok(source_map)
+}
+
+fn utf16_len(text: &str) -> u32 {
+    text.chars().map(|c| c.len_utf16() as u32).sum()
+}
+
+/// The UTF-16 columns where each word or punctuation run of a line starts
+fn token_starts(text: &str) -> Vec<u32> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    let mut starts = vec![];
+    let mut column = 0;
+    let mut previous: Option<char> = None;
+    for c in text.chars() {
+        let starts_token = !c.is_whitespace() && match previous {
+            None => true,
+            Some(previous) => previous.is_whitespace() || is_word(previous) != is_word(c)
+        };
+        if starts_token {
+            starts.push(column);
+        }
+        column += c.len_utf16() as u32;
+        previous = Some(c);
+    }
+    starts
+}
@@ -0,0 +1,72 @@
+use deno_runtime::deno_core;
+use deno_core::ModuleSpecifier;
+use deno_core::serde_json;
+
+use mtsc::util::OptionSource;
+
+use serde::{Deserialize,Serialize};
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::util::hash;
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    code: String,
+    source_map: Option<String>,
+    /// Files pulled in with `#include` and the hash of their contents when the module was compiled
+    includes: Vec<(PathBuf, String)>,
+}
+
+/// mtsc output stored on disk so unchanged modules are not compiled again
+#[derive(Clone)]
+pub struct TranspileCache {
+    dir: PathBuf,
+}
+
+impl TranspileCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Combines everything that can change mtsc's output for a module. The other options mtsc picks, such as
+    /// JSX and TSX handling, follow from `opt_source`, so the same URL served with another media type is compiled again
+    pub fn key(specifier: &ModuleSpecifier, source: &str, opt_source: &OptionSource, options: &mtsc::Options) -> String {
+        let flags = format!("module={} preprocess={} transpile={}", options.module, options.preprocess, options.transpile);
+        let opt_source = match opt_source {
+            OptionSource::Mime(mime_type) => format!("mime={}", mime_type),
+            OptionSource::Path(path) => format!("path={}", path.display()),
+            _ => String::from("none")
+        };
+        hash([
+            hash(source).as_str(),
+            specifier.as_str(),
+            opt_source.as_str(),
+            flags.as_str(),
+            options.macros.join("\n").as_str(),
+            options.include_paths.join("\n").as_str(),
+            mtsc::version(),
+            crate::version(),
+        ].join("\0"))
+    }
+
+    /// Returns the cached code and source map unless an included file has changed since it was compiled
+    pub fn get(&self, key: &str) -> Option<(String, Option<Vec<u8>>)> {
+        let entry: CacheEntry = serde_json::from_slice(&fs::read(self.dir.join(key)).ok()?).ok()?;
+        let unchanged = entry.includes.iter().all(|(path, expected)| fs::read(path).is_ok_and(|contents| &hash(contents) == expected));
+        unchanged.then(|| (entry.code, entry.source_map.map(String::into_bytes)))
+    }
+
+    /// Failing to write the cache only costs a recompile next time, so errors are ignored
+    pub fn set(&self, key: &str, code: &str, source_map: Option<&[u8]>, includes: Vec<PathBuf>) {
+        let entry = CacheEntry {
+            code: code.to_string(),
+            source_map: source_map.map(|source_map| String::from_utf8_lossy(source_map).into_owned()),
+            includes: includes.into_iter().filter_map(|path| fs::read(&path).ok().map(|contents| (path, hash(contents)))).collect(),
+        };
+        if let Ok(json) = serde_json::to_vec(&entry) {
+            let _ = fs::create_dir_all(&self.dir).and_then(|_| fs::write(self.dir.join(key), json));
+        }
+    }
+}
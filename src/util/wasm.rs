@@ -0,0 +1,156 @@
+use deno_runtime::deno_core;
+use deno_core::error::{generic_error,AnyError};
+
+const IMPORT_SECTION: u8 = 2;
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, AnyError> {
+        let byte = *self.bytes.get(self.pos).ok_or_else(|| generic_error("Unexpected end of WebAssembly module"))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn leb128(&mut self) -> Result<u64, AnyError> {
+        let mut result = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            result |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+            shift += 7;
+            if shift >= 64 {
+                return Err(generic_error("Invalid integer in WebAssembly module"));
+            }
+        }
+    }
+
+    /// The position `len` bytes ahead, which must not be past the end of the module
+    fn end(&self, len: u64) -> Result<usize, AnyError> {
+        usize::try_from(len).ok()
+            .and_then(|len| self.pos.checked_add(len))
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| generic_error("Unexpected end of WebAssembly module"))
+    }
+
+    fn name(&mut self) -> Result<String, AnyError> {
+        let len = self.leb128()?;
+        let end = self.end(len)?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(String::from_utf8(bytes.to_vec())?)
+    }
+
+    fn limits(&mut self) -> Result<(), AnyError> {
+        let flags = self.byte()?;
+        self.leb128()?;
+        if flags & 1 != 0 {
+            self.leb128()?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists the distinct module names a WebAssembly binary imports from, in order of first use
+pub fn module_imports(bytes: &[u8]) -> Result<Vec<String>, AnyError> {
+    if bytes.len() < 8 || &bytes[0..4] != b"\0asm" {
+        return Err(generic_error("Invalid WebAssembly module"));
+    }
+
+    let mut reader = Reader { bytes, pos: 8 };
+    let mut modules = vec![];
+
+    while reader.pos < bytes.len() {
+        let id = reader.byte()?;
+        let size = reader.leb128()?;
+        let end = reader.end(size)?;
+
+        if id == IMPORT_SECTION {
+            for _ in 0..reader.leb128()? {
+                let module = reader.name()?;
+                reader.name()?;
+                match reader.byte()? {
+                    // Function type index
+                    0x00 => { reader.leb128()?; },
+                    // Table reference type and limits
+                    0x01 => { reader.byte()?; reader.limits()?; },
+                    // Memory limits
+                    0x02 => reader.limits()?,
+                    // Global value type and mutability
+                    0x03 => { reader.byte()?; reader.byte()?; },
+                    // Tag attribute and type index
+                    0x04 => { reader.byte()?; reader.leb128()?; },
+                    kind => return Err(generic_error(format!("Unknown import kind {kind} in WebAssembly module")))
+                }
+                if !modules.contains(&module) {
+                    modules.push(module);
+                }
+            }
+            break;
+        }
+
+        reader.pos = end;
+    }
+
+    Ok(modules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &[u8] = b"\0asm\x01\0\0\0";
+
+    fn module(sections: &[&[u8]]) -> Vec<u8> {
+        let mut bytes = HEADER.to_vec();
+        for section in sections {
+            bytes.extend_from_slice(section);
+        }
+        bytes
+    }
+
+    #[test]
+    fn lists_imported_modules() {
+        let bytes = module(&[
+            // Type section with a single `() -> ()` function type
+            &[0x01, 0x04, 0x01, 0x60, 0x00, 0x00],
+            // Import section with a function, a memory and a global
+            &[0x02, 0x1c, 0x03,
+                0x03, b'e', b'n', b'v', 0x01, b'f', 0x00, 0x00,
+                0x02, b'j', b's', 0x03, b'm', b'e', b'm', 0x02, 0x00, 0x01,
+                0x03, b'e', b'n', b'v', 0x01, b'g', 0x03, 0x7f, 0x00
+            ]
+        ]);
+        assert_eq!(module_imports(&bytes).unwrap(), vec![String::from("env"), String::from("js")]);
+    }
+
+    #[test]
+    fn accepts_modules_without_imports() {
+        assert!(module_imports(HEADER).unwrap().is_empty());
+        assert!(module_imports(&module(&[&[0x01, 0x04, 0x01, 0x60, 0x00, 0x00]])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_modules() {
+        assert!(module_imports(b"not wasm").is_err());
+        assert!(module_imports(b"\0asm").is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_modules() {
+        // Section longer than the module
+        assert!(module_imports(&module(&[&[0x01, 0x7f, 0x00]])).is_err());
+        // Section size that overflows
+        assert!(module_imports(&module(&[&[0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]])).is_err());
+        // Import name longer than the module
+        assert!(module_imports(&module(&[&[0x02, 0x06, 0x01, 0xff, 0xff, 0xff, 0xff, 0x0f]])).is_err());
+        // Import name length that overflows
+        assert!(module_imports(&module(&[&[0x02, 0x0b, 0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]])).is_err());
+    }
+}
@@ -0,0 +1,18 @@
+use tokio::sync::mpsc::UnboundedSender;
+
+use std::path::PathBuf;
+
+/// Reports the local files a program depends on to a watcher, shared by every module loader of the program
+#[derive(Clone)]
+pub struct WatchedFiles(UnboundedSender<PathBuf>);
+
+impl WatchedFiles {
+    pub fn new(sender: UnboundedSender<PathBuf>) -> Self {
+        Self(sender)
+    }
+
+    pub fn add(&self, path: PathBuf) {
+        // The watcher stops listening once it starts restarting, nothing is lost by ignoring that
+        let _ = self.0.send(path);
+    }
+}